@@ -0,0 +1,276 @@
+use crate::asset_resolver::AssetResolver;
+use crate::config::inventory;
+use crate::{DEBUG_TEXTURE, Descriptions, GameData, InventoryData};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+// Checks every asset path referenced by the game data against the search roots
+// once at startup, so missing files can be reported in one place instead of
+// every frame they fail to draw.
+#[derive(Default)]
+pub struct AssetAudit {
+    // Missing path -> everything that references it
+    missing: BTreeMap<String, BTreeSet<String>>,
+    // (level id, scene id) -> missing paths referenced by that scene
+    scene_missing: HashMap<(u32, u32), Vec<String>>,
+    checked: HashMap<String, bool>,
+    roots: Vec<String>,
+}
+
+impl AssetAudit {
+    pub fn run(data: &GameData, resolver: &AssetResolver) -> Self {
+        let mut audit = AssetAudit {
+            roots: resolver
+                .roots()
+                .iter()
+                .map(|root| format!("{} ({})", root.path.display(), root.source))
+                .collect(),
+            ..Default::default()
+        };
+
+        for level in &data.levels {
+            for scene in &level.scenes {
+                let owner = format!("{} #{} {}", level.name, scene.id, scene.name);
+                let key = Some((level.id, scene.id));
+
+                audit.check(resolver, &scene.background, &owner, "background", key);
+                for overlay in &scene.overlay_assets {
+                    audit.check(resolver, &overlay.texture_path, &owner, "overlay", key);
+                }
+                for music in scene.music_tracks() {
+                    audit.check(resolver, &music, &owner, "music", key);
+                }
+                for layer in &scene.ambient_layers {
+                    audit.check(resolver, layer, &owner, "ambience", key);
+                }
+                for source in &scene.ambient_sources {
+                    audit.check(resolver, &source.audio, &owner, "ambient source", key);
+                }
+                for descriptions in scene.descriptions() {
+                    audit.check_descriptions(resolver, descriptions, &owner, key);
+                }
+                for instance in &scene.items {
+                    let Some(item) = data.items.iter().find(|i| i.id == instance.item_id) else {
+                        continue;
+                    };
+                    let what = format!("item {}", item.name);
+                    for path in [&item.textures.in_world, &item.textures.mouse_over] {
+                        audit.check(resolver, path, &owner, &what, key);
+                    }
+                }
+                for npc in &scene.npcs {
+                    let what = format!("npc {}", npc.name);
+                    for path in npc.texture_paths() {
+                        audit.check(resolver, &path, &owner, &what, key);
+                    }
+                }
+                for sequence in &scene.sequences {
+                    let what = format!("sequence {}", sequence.name);
+                    for path in sequence.textures().chain(sequence.audio()) {
+                        audit.check(resolver, path, &owner, &what, key);
+                    }
+                }
+                for dialog in &scene.dialogs {
+                    let what = format!("dialog {}", dialog.description);
+                    if let Some(open_audio) = &dialog.open_audio {
+                        audit.check(resolver, &scene.voice_path(open_audio), &owner, &what, key);
+                    }
+                    for node in &dialog.tree {
+                        for option in &node.options {
+                            for audio in &option.response_audio {
+                                let path = scene.voice_path(audio);
+                                audit.check(resolver, &path, &owner, &what, key);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        for item in &data.items {
+            let owner = format!("item {}", item.name);
+            let textures = &item.textures;
+            for path in [&textures.in_inventory, &textures.in_inventory_text] {
+                audit.check(resolver, path, &owner, "texture", None);
+            }
+            // Items placed in a scene had their world textures checked with it,
+            // the rest only show up in the world when dropped from the inventory
+            let placed = data
+                .levels
+                .iter()
+                .flat_map(|level| &level.scenes)
+                .any(|scene| scene.items.iter().any(|i| i.item_id == item.id));
+            if !placed {
+                for path in [&textures.in_world, &textures.mouse_over] {
+                    audit.check(resolver, path, &owner, "texture", None);
+                }
+            }
+            audit.check_descriptions(resolver, &item.descriptions, &owner, None);
+            for (character, audio_files) in &item.pickup_audio {
+                for path in audio_files {
+                    audit.check(
+                        resolver,
+                        path,
+                        &owner,
+                        &format!("{} pickup", character),
+                        None,
+                    );
+                }
+            }
+        }
+
+        for video in &data.videos {
+            let owner = format!("video {}", video.name);
+            audit.check(resolver, &video.frame_path(0), &owner, "first frame", None);
+            if let Some(audio) = &video.audio {
+                audit.check(resolver, audio, &owner, "audio", None);
+            }
+        }
+
+        for recipe in &data.recipes {
+            let owner = format!("recipe for item {}", recipe.result);
+            for (character, audio_files) in &recipe.audio {
+                for path in audio_files {
+                    audit.check(resolver, path, &owner, character, None);
+                }
+            }
+            if let Some(sound) = &recipe.sound {
+                audit.check(resolver, sound, &owner, "sound", None);
+            }
+        }
+
+        for character in &data.characters {
+            let owner = format!("character {}", character.name);
+            for direction in 1..=8 {
+                for frame in 0..=7 {
+                    for state in [0, 7] {
+                        let path = character.frame_path(direction, frame, state);
+                        audit.check(resolver, &path, &owner, "frame", None);
+                    }
+                }
+                if let Some(talk) = &character.talk_frames {
+                    for frame in 0..talk.count {
+                        let path = character.frame_path(direction, frame, talk.state);
+                        audit.check(resolver, &path, &owner, "talk frame", None);
+                    }
+                }
+            }
+            for path in &character.select_audio {
+                audit.check(resolver, path, &owner, "select", None);
+            }
+        }
+
+        for (surface, audio_files) in &data.footsteps {
+            let what = format!("{:?} footstep", surface);
+            for path in audio_files {
+                audit.check(resolver, path, "footsteps", &what, None);
+            }
+        }
+
+        for cursor in &data.ui.cursors {
+            audit.check(resolver, &cursor.texture, "ui", "cursor", None);
+        }
+        for menu_item in &data.ui.menu_items {
+            audit.check(resolver, &menu_item.texture, "ui", &menu_item.name, None);
+        }
+        let dialog_background = &data.ui.general_textures.dialog_background;
+        audit.check(resolver, dialog_background, "ui", "dialog background", None);
+
+        for frame in 0..inventory::BUTTON_FRAMES {
+            let path = InventoryData::button_texture_path(frame);
+            audit.check(resolver, &path, "inventory", "button", None);
+        }
+        for path in [
+            inventory::LEFT_ARROW_TEXTURE,
+            inventory::RIGHT_ARROW_TEXTURE,
+            inventory::OPEN_SOUND,
+            inventory::CLOSE_SOUND,
+        ] {
+            audit.check(resolver, path, "inventory", "ui", None);
+        }
+        audit.check(resolver, DEBUG_TEXTURE, "debug", "texture", None);
+
+        audit
+    }
+
+    fn check(
+        &mut self,
+        resolver: &AssetResolver,
+        path: &str,
+        owner: &str,
+        what: &str,
+        scene: Option<(u32, u32)>,
+    ) {
+        let found = *self
+            .checked
+            .entry(path.to_string())
+            .or_insert_with(|| resolver.resolve_resource(path).is_ok());
+        if found {
+            return;
+        }
+
+        self.missing
+            .entry(path.to_string())
+            .or_default()
+            .insert(format!("{} {}", owner, what));
+        if let Some(key) = scene {
+            let scene_missing = self.scene_missing.entry(key).or_default();
+            if !scene_missing.iter().any(|p| p == path) {
+                scene_missing.push(path.to_string());
+            }
+        }
+    }
+
+    fn check_descriptions(
+        &mut self,
+        resolver: &AssetResolver,
+        descriptions: &Descriptions,
+        owner: &str,
+        scene: Option<(u32, u32)>,
+    ) {
+        for (character, description) in descriptions {
+            let what = format!("{} description", character);
+            for path in &description.audio {
+                self.check(resolver, path, owner, &what, scene);
+            }
+        }
+    }
+
+    pub fn missing_count(&self) -> usize {
+        self.missing.len()
+    }
+
+    pub fn checked_count(&self) -> usize {
+        self.checked.len()
+    }
+
+    pub fn scene_missing(&self, level_id: u32, scene_id: u32) -> &[String] {
+        self.scene_missing
+            .get(&(level_id, scene_id))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn report(&self) -> String {
+        let mut report = String::from("OpenJönsson missing asset report\n\nSearched roots:\n");
+        for root in &self.roots {
+            report.push_str(&format!("  {}\n", root));
+        }
+        report.push_str(&format!(
+            "\n{} of {} referenced assets missing\n",
+            self.missing_count(),
+            self.checked_count()
+        ));
+        for (path, used_by) in &self.missing {
+            report.push_str(&format!("\n{}\n", path));
+            for user in used_by {
+                report.push_str(&format!("    {}\n", user));
+            }
+        }
+        report
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn write_report(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.report())
+    }
+}
@@ -1,31 +1,70 @@
-use macroquad::audio::{load_sound, Sound};
+use crate::asset_resolver::AssetResolver;
+use crate::config::{audio, voice};
+use crate::error::{AssetError, DataError};
+use crate::logging;
+use macroquad::audio::{Sound, load_sound_from_bytes};
 use macroquad::prelude::*;
+use serde::de::DeserializeOwned;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::task::{Context, Poll, Waker};
 
 pub struct AssetManager {
+    resolver: AssetResolver,
     textures: HashMap<String, Texture2D>,
     pub sounds: HashMap<String, Sound>,
+    // WAV data of positional sounds, which aren't in `sounds`. A stereo copy
+    // panned to where the sound plays is rendered from it when needed.
+    positional_sounds: HashMap<String, Vec<u8>>,
+    // The last rendered copy of each positional sound and its pan step
+    panned_sounds: RefCell<HashMap<String, (usize, Sound)>>,
+    sound_durations: HashMap<String, f32>,
+    // Loudness of short clips sampled at voice::ENVELOPE_RATE, normalized to 0..1
+    sound_envelopes: HashMap<String, Vec<f32>>,
     loading_textures: Vec<String>,
     fonts: HashMap<String, Font>,
 }
 
 impl AssetManager {
-    pub fn new() -> Self {
+    pub fn new(resolver: AssetResolver) -> Self {
         AssetManager {
+            resolver,
             textures: HashMap::new(),
             sounds: HashMap::new(),
+            positional_sounds: HashMap::new(),
+            panned_sounds: RefCell::new(HashMap::new()),
+            sound_durations: HashMap::new(),
+            sound_envelopes: HashMap::new(),
             loading_textures: Vec::new(),
             fonts: HashMap::new(),
         }
     }
 
-    pub async fn load_texture(&mut self, path: &str) -> Result<(), String> {
+    pub fn resolver(&self) -> &AssetResolver {
+        &self.resolver
+    }
+
+    pub async fn load_data_string(&self, path: &str) -> Result<String, AssetError> {
+        let full_path = self.resolver.resolve_data(path)?;
+        load_string(&full_path).await.map_err(|e| AssetError::Data {
+            path: full_path,
+            message: e.to_string(),
+        })
+    }
+
+    pub async fn load_data_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, DataError> {
+        let json = self.load_data_string(path).await?;
+        let deserializer = &mut serde_json::Deserializer::from_str(&json);
+        serde_path_to_error::deserialize(deserializer).map_err(|e| DataError::from_json(path, e))
+    }
+
+    pub async fn load_texture(&mut self, path: &str) -> Result<(), AssetError> {
         if self.textures.contains_key(path) || self.loading_textures.contains(&path.to_string()) {
             return Ok(());
         }
 
+        let full_path = self.resolver.resolve_resource(path)?;
         self.loading_textures.push(path.to_string());
-        let full_path = format!("static/resources/{}", path);
         match load_texture(&full_path).await {
             Ok(texture) => {
                 self.textures.insert(path.to_string(), texture);
@@ -34,48 +73,167 @@ impl AssetManager {
             }
             Err(e) => {
                 self.loading_textures.retain(|x| x != path);
-                Err(format!("Failed to load texture {}: {}", path, e))
+                Err(AssetError::Texture {
+                    path: path.to_string(),
+                    message: e.to_string(),
+                })
             }
         }
     }
 
-    pub async fn load_sound(&mut self, path: &str) -> Result<(), String> {
-        if self.sounds.contains_key(path) {
+    pub async fn load_sound(&mut self, path: &str) -> Result<(), AssetError> {
+        if self.sounds.contains_key(path) || self.positional_sounds.contains_key(path) {
             return Ok(());
         }
-        let full_path = format!("static/resources/{}", path);
-        match load_sound(&full_path).await {
-            Ok(sound) => {
-                self.sounds.insert(path.to_string(), sound);
-                Ok(())
+        let bytes = self.load_sound_bytes(path).await?;
+        self.insert_sound(path, &bytes).await
+    }
+
+    // Loads a sound that can be played at a position on screen. macroquad has no
+    // panning, so the WAV data is kept and get_panned_sound renders a stereo
+    // copy of it for where the sound plays.
+    pub async fn load_positional_sound(&mut self, path: &str) -> Result<(), AssetError> {
+        if self.positional_sounds.contains_key(path) {
+            return Ok(());
+        }
+        let bytes = self.load_sound_bytes(path).await?;
+
+        // The web build can't create a sound without waiting for it
+        if cfg!(target_arch = "wasm32") {
+            return self.insert_sound(path, &bytes).await;
+        }
+        if !can_pan(&bytes) {
+            log::debug!(target: logging::ASSETS, "Can't pan {}, playing it centered", path);
+            return self.insert_sound(path, &bytes).await;
+        }
+        self.insert_sound_info(path, &bytes);
+        // The plain copy is no longer needed if it was loaded before
+        self.sounds.remove(path);
+        self.positional_sounds.insert(path.to_string(), bytes);
+        Ok(())
+    }
+
+    async fn load_sound_bytes(&self, path: &str) -> Result<Vec<u8>, AssetError> {
+        let full_path = self.resolver.resolve_resource(path)?;
+        load_file(&full_path).await.map_err(|e| AssetError::Sound {
+            path: path.to_string(),
+            message: e.to_string(),
+        })
+    }
+
+    async fn insert_sound(&mut self, path: &str, bytes: &[u8]) -> Result<(), AssetError> {
+        let sound = load_sound_from_bytes(bytes)
+            .await
+            .map_err(|e| AssetError::Sound {
+                path: path.to_string(),
+                message: e.to_string(),
+            })?;
+
+        self.insert_sound_info(path, bytes);
+        self.sounds.insert(path.to_string(), sound);
+        Ok(())
+    }
+
+    fn insert_sound_info(&mut self, path: &str, bytes: &[u8]) {
+        // macroquad can't report playback progress, so the length is read
+        // from the WAV header to know when a clip has finished.
+        if let Some(duration) = wav_duration(bytes) {
+            self.sound_durations.insert(path.to_string(), duration);
+            // Long clips are music and never drive a talk animation
+            if duration <= voice::ENVELOPE_MAX_LENGTH
+                && let Some(envelope) = wav_envelope(bytes)
+            {
+                self.sound_envelopes.insert(path.to_string(), envelope);
             }
-            Err(e) => Err(format!("Failed to load sound {}: {}", path, e)),
         }
     }
 
+    // Frees a texture that won't be drawn again, like a shown video frame
+    pub fn unload_texture(&mut self, path: &str) {
+        self.textures.remove(path);
+    }
+
     pub fn get_texture(&self, path: &str) -> Option<&Texture2D> {
         self.textures.get(path)
     }
 
-    pub fn get_sound(&self, path: &str) -> Option<&Sound> {
-        self.sounds.get(path)
+    pub fn get_sound(&self, path: &str) -> Option<Sound> {
+        self.sounds
+            .get(path)
+            .cloned()
+            .or_else(|| self.get_panned_sound(path, 0.0))
+    }
+
+    // A positional sound panned to the step closest to `pan`, from -PAN_WIDTH
+    // (left) to PAN_WIDTH (right). Only the last rendered copy of each clip is
+    // cached, copies still playing are kept alive by their voices.
+    pub fn get_panned_sound(&self, path: &str, pan: f32) -> Option<Sound> {
+        let bytes = self.positional_sounds.get(path)?;
+        let steps = (audio::PAN_STEPS - 1) as f32;
+        let pan = (pan / audio::PAN_WIDTH).clamp(-1.0, 1.0);
+        let step = ((pan + 1.0) / 2.0 * steps).round() as usize;
+
+        let mut cache = self.panned_sounds.borrow_mut();
+        if let Some((cached_step, sound)) = cache.get(path)
+            && *cached_step == step
+        {
+            return Some(sound.clone());
+        }
+        let pan = (step as f32 / steps * 2.0 - 1.0) * audio::PAN_WIDTH;
+        let sound = pan_wav(bytes, pan).and_then(|panned| load_sound_now(&panned))?;
+        cache.insert(path.to_string(), (step, sound.clone()));
+        Some(sound)
+    }
+
+    // Length in seconds, if the sound is a readable WAV file
+    pub fn get_sound_duration(&self, path: &str) -> Option<f32> {
+        self.sound_durations.get(path).copied()
+    }
+
+    // Loudness in 0..1 at `time` seconds into the clip
+    pub fn get_sound_amplitude(&self, path: &str, time: f32) -> Option<f32> {
+        let envelope = self.sound_envelopes.get(path)?;
+        let index = (time.max(0.0) * voice::ENVELOPE_RATE as f32) as usize;
+        Some(envelope.get(index).copied().unwrap_or(0.0))
     }
 
     pub async fn load_textures(&mut self, paths: &[String]) {
         for path in paths {
             if let Err(e) = self.load_texture(path).await {
-                eprintln!("{}", e);
+                log::warn!(target: logging::ASSETS, "{}", e);
+            }
+        }
+    }
+
+    // Clips referenced by the level data are optional: missing ones are listed
+    // by the startup asset audit and the game plays on without them
+    pub async fn load_sounds(&mut self, paths: impl IntoIterator<Item = &String>) {
+        for path in paths {
+            if let Err(e) = self.load_sound(path).await {
+                log::warn!(target: logging::ASSETS, "{}", e);
             }
         }
     }
 
-    pub async fn load_font(&mut self, name: &str, path: &str) -> Result<(), String> {
-        match load_ttf_font(path).await {
+    pub async fn load_positional_sounds(&mut self, paths: impl IntoIterator<Item = &String>) {
+        for path in paths {
+            if let Err(e) = self.load_positional_sound(path).await {
+                log::warn!(target: logging::ASSETS, "{}", e);
+            }
+        }
+    }
+
+    pub async fn load_font(&mut self, name: &str, path: &str) -> Result<(), AssetError> {
+        let full_path = self.resolver.resolve_data(path)?;
+        match load_ttf_font(&full_path).await {
             Ok(font) => {
                 self.fonts.insert(name.to_string(), font);
                 Ok(())
             }
-            Err(e) => Err(format!("Failed to load font {} from {}: {}", name, path, e)),
+            Err(e) => Err(AssetError::Font {
+                name: name.to_string(),
+                message: format!("{}: {}", full_path, e),
+            }),
         }
     }
 
@@ -83,3 +241,88 @@ impl AssetManager {
         self.fonts.get(name)
     }
 }
+
+fn wav_duration(bytes: &[u8]) -> Option<f32> {
+    let reader = hound::WavReader::new(std::io::Cursor::new(bytes)).ok()?;
+    let sample_rate = reader.spec().sample_rate;
+    (sample_rate > 0).then(|| reader.duration() as f32 / sample_rate as f32)
+}
+
+// macroquad loads sounds from memory right away on native targets, so the
+// future is ready the first time it is polled
+fn load_sound_now(bytes: &[u8]) -> Option<Sound> {
+    let mut future = std::pin::pin!(load_sound_from_bytes(bytes));
+    match future
+        .as_mut()
+        .poll(&mut Context::from_waker(Waker::noop()))
+    {
+        Poll::Ready(sound) => sound.ok(),
+        Poll::Pending => None,
+    }
+}
+
+// Like the envelope, panning only handles integer PCM up to 16 bits
+fn can_pan(bytes: &[u8]) -> bool {
+    hound::WavReader::new(std::io::Cursor::new(bytes)).is_ok_and(|reader| {
+        let spec = reader.spec();
+        spec.sample_format == hound::SampleFormat::Int && spec.bits_per_sample <= 16
+    })
+}
+
+// Renders a mono mix of the clip into stereo with constant power panning
+fn pan_wav(bytes: &[u8], pan: f32) -> Option<Vec<u8>> {
+    if !can_pan(bytes) {
+        return None;
+    }
+    let mut reader = hound::WavReader::new(std::io::Cursor::new(bytes)).ok()?;
+    let spec = reader.spec();
+    let channels = spec.channels as usize;
+    // 8 bit samples are widened to 16 bits
+    let to_16_bit = (1 << (16 - spec.bits_per_sample)) as f32;
+    let samples: Vec<i16> = reader.samples::<i16>().filter_map(Result::ok).collect();
+
+    let angle = (pan.clamp(-1.0, 1.0) + 1.0) * std::f32::consts::FRAC_PI_4;
+    let (left_gain, right_gain) = (angle.cos(), angle.sin());
+    let out_spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: spec.sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut cursor = std::io::Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut cursor, out_spec).ok()?;
+    for frame in samples.chunks(channels) {
+        let mono = frame.iter().map(|&s| s as f32).sum::<f32>() / frame.len() as f32 * to_16_bit;
+        writer.write_sample((mono * left_gain) as i16).ok()?;
+        writer.write_sample((mono * right_gain) as i16).ok()?;
+    }
+    writer.finalize().ok()?;
+    Some(cursor.into_inner())
+}
+
+// RMS loudness per window, scaled so the loudest window is 1.0.
+// Only integer PCM up to 16 bits is analyzed, which covers the game's voice clips.
+fn wav_envelope(bytes: &[u8]) -> Option<Vec<f32>> {
+    let mut reader = hound::WavReader::new(std::io::Cursor::new(bytes)).ok()?;
+    let spec = reader.spec();
+    if spec.sample_format != hound::SampleFormat::Int || spec.bits_per_sample > 16 {
+        return None;
+    }
+    let full_scale = (1i32 << (spec.bits_per_sample - 1)) as f32;
+    let window = (spec.sample_rate as usize / voice::ENVELOPE_RATE).max(1) * spec.channels as usize;
+    let samples: Vec<f32> = reader
+        .samples::<i16>()
+        .filter_map(Result::ok)
+        .map(|s| s as f32 / full_scale)
+        .collect();
+
+    let mut envelope: Vec<f32> = samples
+        .chunks(window)
+        .map(|chunk| (chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len() as f32).sqrt())
+        .collect();
+    let peak = envelope.iter().copied().fold(0.0, f32::max);
+    if peak > 0.0 {
+        envelope.iter_mut().for_each(|v| *v /= peak);
+    }
+    Some(envelope)
+}
@@ -0,0 +1,238 @@
+use crate::config::paths;
+use crate::error::AssetError;
+use crate::logging;
+use serde::Deserialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+// Where a search root came from. Roots are searched in the order they were added,
+// which is also the order of precedence: command line, environment, config file,
+// XDG data directories and finally the directories next to the executable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RootSource {
+    CommandLine,
+    Environment,
+    ConfigFile,
+    XdgData,
+    Executable,
+    WorkingDirectory,
+}
+
+impl fmt::Display for RootSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RootSource::CommandLine => write!(f, "{} flag", paths::DATA_DIR_FLAG),
+            RootSource::Environment => write!(f, "${}", paths::DATA_DIR_ENV),
+            RootSource::ConfigFile => write!(f, "{}", paths::PATHS_CONFIG_FILE),
+            RootSource::XdgData => write!(f, "XDG data dir"),
+            RootSource::Executable => write!(f, "next to executable"),
+            RootSource::WorkingDirectory => write!(f, "working directory"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchRoot {
+    pub path: PathBuf,
+    pub source: RootSource,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Deserialize, Debug, Default)]
+struct PathsConfig {
+    #[serde(default)]
+    data_dirs: Vec<PathBuf>,
+}
+
+// A root is a directory laid out like the repository's `static` folder:
+// data files such as `level_data.json` at the top, extracted game assets
+// in `resources/` and fonts in `fonts/`.
+pub struct AssetResolver {
+    roots: Vec<SearchRoot>,
+}
+
+impl AssetResolver {
+    pub fn new(roots: Vec<SearchRoot>) -> Self {
+        AssetResolver { roots }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_environment() -> Self {
+        let mut resolver = AssetResolver::new(Vec::new());
+
+        for path in Self::command_line_roots(std::env::args().skip(1)) {
+            resolver.add_root(path, RootSource::CommandLine);
+        }
+
+        if let Some(value) = std::env::var_os(paths::DATA_DIR_ENV) {
+            for path in std::env::split_paths(&value) {
+                resolver.add_root(path, RootSource::Environment);
+            }
+        }
+
+        if let Some(config_file) = config_dir().map(|dir| dir.join(paths::PATHS_CONFIG_FILE)) {
+            for path in Self::config_file_roots(&config_file) {
+                resolver.add_root(path, RootSource::ConfigFile);
+            }
+        }
+
+        for path in xdg_data_dirs() {
+            resolver.add_root(path.join(paths::APP_DIR_NAME), RootSource::XdgData);
+        }
+
+        if let Some(exe_dir) = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        {
+            resolver.add_root(exe_dir.join(paths::DEFAULT_ROOT), RootSource::Executable);
+        }
+
+        resolver.add_root(
+            PathBuf::from(paths::DEFAULT_ROOT),
+            RootSource::WorkingDirectory,
+        );
+        resolver
+    }
+
+    // The browser can only fetch files relative to the page, so the web build
+    // always uses the default root.
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_environment() -> Self {
+        AssetResolver::new(vec![SearchRoot {
+            path: PathBuf::from(paths::DEFAULT_ROOT),
+            source: RootSource::WorkingDirectory,
+        }])
+    }
+
+    pub fn add_root(&mut self, path: PathBuf, source: RootSource) {
+        if path.as_os_str().is_empty() || self.roots.iter().any(|r| r.path == path) {
+            return;
+        }
+        self.roots.push(SearchRoot { path, source });
+    }
+
+    pub fn roots(&self) -> &[SearchRoot] {
+        &self.roots
+    }
+
+    // Resolves a file relative to a root, e.g. `level_data.json` or `fonts/x.ttf`.
+    pub fn resolve_data(&self, path: &str) -> Result<String, AssetError> {
+        self.resolve(Path::new(path))
+    }
+
+    // Resolves an extracted game asset, e.g. `Huvudmeny/ljudfx/oppna.wav`.
+    pub fn resolve_resource(&self, path: &str) -> Result<String, AssetError> {
+        self.resolve(&Path::new(paths::RESOURCE_DIR).join(path))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn resolve(&self, relative: &Path) -> Result<String, AssetError> {
+        self.roots
+            .iter()
+            .map(|root| root.path.join(relative))
+            .find(|candidate| candidate.is_file())
+            .map(|found| found.to_string_lossy().into_owned())
+            .ok_or_else(|| self.not_found(relative))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn resolve(&self, relative: &Path) -> Result<String, AssetError> {
+        self.roots
+            .first()
+            .map(|root| root.path.join(relative).to_string_lossy().into_owned())
+            .ok_or_else(|| self.not_found(relative))
+    }
+
+    fn not_found(&self, relative: &Path) -> AssetError {
+        AssetError::NotFound {
+            path: relative.display().to_string(),
+            searched: self
+                .roots
+                .iter()
+                .map(|root| format!("{} ({})", root.path.display(), root.source))
+                .collect(),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn command_line_roots(mut args: impl Iterator<Item = String>) -> Vec<PathBuf> {
+        let mut roots = Vec::new();
+        while let Some(arg) = args.next() {
+            if arg == paths::DATA_DIR_FLAG {
+                if let Some(value) = args.next() {
+                    roots.push(PathBuf::from(value));
+                }
+            } else if let Some(value) = arg
+                .strip_prefix(paths::DATA_DIR_FLAG)
+                .and_then(|rest| rest.strip_prefix('='))
+            {
+                roots.push(PathBuf::from(value));
+            }
+        }
+        roots
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn config_file_roots(config_file: &Path) -> Vec<PathBuf> {
+        let Ok(json) = std::fs::read_to_string(config_file) else {
+            return Vec::new();
+        };
+        match serde_json::from_str::<PathsConfig>(&json) {
+            Ok(config) => config.data_dirs,
+            Err(e) => {
+                log::warn!(
+                    target: logging::ASSETS,
+                    "Ignoring {}: {}",
+                    config_file.display(),
+                    e
+                );
+                Vec::new()
+            }
+        }
+    }
+}
+
+// Per-user configuration directory for OpenJönsson, following the XDG base
+// directory spec on Linux and the platform conventions elsewhere.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home_dir().map(|home| home.join("Library/Application Support"))
+    } else {
+        non_empty_env("XDG_CONFIG_HOME").or_else(|| home_dir().map(|home| home.join(".config")))
+    };
+    base.map(|dir| dir.join(paths::APP_DIR_NAME))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn xdg_data_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(data_home) =
+        non_empty_env("XDG_DATA_HOME").or_else(|| home_dir().map(|h| h.join(".local/share")))
+    {
+        dirs.push(data_home);
+    }
+    match std::env::var_os("XDG_DATA_DIRS").filter(|v| !v.is_empty()) {
+        Some(value) => dirs.extend(std::env::split_paths(&value)),
+        None if cfg!(unix) => {
+            dirs.push(PathBuf::from("/usr/local/share"));
+            dirs.push(PathBuf::from("/usr/share"));
+        }
+        None => {}
+    }
+    dirs
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn home_dir() -> Option<PathBuf> {
+    non_empty_env("HOME").or_else(|| non_empty_env("USERPROFILE"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn non_empty_env(key: &str) -> Option<PathBuf> {
+    std::env::var_os(key)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
@@ -1,29 +1,156 @@
 use crate::asset_manager::AssetManager;
-use macroquad::audio::{play_sound, set_sound_volume, stop_sound, Sound};
+use crate::config::{audio, voice};
+use crate::error::AudioError;
+use crate::logging;
+use macroquad::audio::{Sound, play_sound, set_sound_volume, stop_sound};
+use macroquad::math::Vec2;
+use macroquad::time::get_time;
 use std::collections::HashMap;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum AudioCategory {
     Music,
+    Ambience,
     Dialog,
     SoundEffect,
 }
 
+impl AudioCategory {
+    pub const ALL: [AudioCategory; 4] = [
+        AudioCategory::Music,
+        AudioCategory::Ambience,
+        AudioCategory::Dialog,
+        AudioCategory::SoundEffect,
+    ];
+}
+
+// Every category has its own bus, and all of them feed into the master bus
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Bus {
+    Master,
+    Category(AudioCategory),
+}
+
+// Where a sound comes from in game space. The x position pans the sound between
+// the speakers, and attenuated emitters also get quieter the further they are
+// from the listener.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Emitter {
+    pub position: Vec2,
+    pub attenuate: bool,
+}
+
+impl Emitter {
+    pub fn at(position: Vec2) -> Self {
+        Emitter {
+            position,
+            attenuate: false,
+        }
+    }
+
+    pub fn attenuated(position: Vec2) -> Self {
+        Emitter {
+            position,
+            attenuate: true,
+        }
+    }
+
+    fn pan(&self) -> f32 {
+        ((self.position.x / GAME_WIDTH) * 2.0 - 1.0).clamp(-1.0, 1.0) * audio::PAN_WIDTH
+    }
+
+    fn gain(&self, listener: Vec2) -> f32 {
+        if !self.attenuate {
+            return 1.0;
+        }
+        let distance = self.position.distance(listener);
+        let t = ((distance - audio::ATTENUATION_START)
+            / (audio::ATTENUATION_END - audio::ATTENUATION_START))
+            .clamp(0.0, 1.0);
+        1.0 - t * (1.0 - audio::ATTENUATION_MIN_GAIN)
+    }
+}
+
+const GAME_WIDTH: f32 = 1920.0;
+
+#[derive(Clone, Copy, Debug)]
+struct BusState {
+    volume: f32,
+    muted: bool,
+}
+
+impl BusState {
+    fn gain(&self) -> f32 {
+        if self.muted { 0.0 } else { self.volume }
+    }
+}
+
+// A sound that is currently playing. The handle is kept so the voice can be
+// stopped and have its volume changed without going through the AssetManager.
+struct Voice {
+    name: String,
+    sound: Sound,
+    category: AudioCategory,
+    started_at: f64,
+    duration: Option<f32>,
+    looped: bool,
+    // Fade gain in 0..1 and how fast it changes per second
+    fade: f32,
+    fade_rate: f32,
+    stop_when_silent: bool,
+    emitter: Option<Emitter>,
+    // Distance attenuation of the emitter
+    gain: f32,
+}
+
+impl Voice {
+    // Clips without a known length are given a guessed one, so they don't keep
+    // the music ducked or show up as playing forever
+    fn is_finished(&self, now: f64) -> bool {
+        if self.looped {
+            return false;
+        }
+        let duration = self.duration.unwrap_or(match self.category {
+            AudioCategory::Dialog => voice::UNKNOWN_LINE_LENGTH,
+            _ => audio::AMBIENT_FADE,
+        });
+        now - self.started_at >= duration as f64
+    }
+}
+
 pub struct AudioSystem {
-    volume_levels: HashMap<AudioCategory, f32>,
-    pub currently_playing: HashMap<AudioCategory, Option<String>>,
+    master: BusState,
+    buses: HashMap<AudioCategory, BusState>,
+    voices: Vec<Voice>,
+    // Extra gain on the music bus, lowered while dialog is playing
+    music_duck: f32,
+    listener: Vec2,
 }
 
 impl AudioSystem {
     pub fn new() -> Self {
-        let mut volume_levels = HashMap::new();
-        volume_levels.insert(AudioCategory::Music, 1.0);
-        volume_levels.insert(AudioCategory::Dialog, 1.0);
-        volume_levels.insert(AudioCategory::SoundEffect, 1.0);
+        let buses = AudioCategory::ALL
+            .iter()
+            .map(|&category| {
+                (
+                    category,
+                    BusState {
+                        volume: 1.0,
+                        muted: false,
+                    },
+                )
+            })
+            .collect();
 
         AudioSystem {
-            volume_levels,
-            currently_playing: HashMap::new(),
+            master: BusState {
+                volume: 1.0,
+                muted: false,
+            },
+            buses,
+            voices: Vec::new(),
+            music_duck: 1.0,
+            listener: Vec2::new(GAME_WIDTH / 2.0, 720.0),
         }
     }
 
@@ -32,86 +159,376 @@ impl AudioSystem {
         asset_manager: &AssetManager,
         name: &str,
         category: AudioCategory,
-    ) {
-        if let Some(sound) = asset_manager.get_sound(name) {
-            let volume = self.volume_levels.get(&category).cloned().unwrap_or(1.0);
-
-            // Stop any currently playing audio in the same category
-            if let Some(current_name) = self.currently_playing.get(&category).cloned().flatten() {
-                if current_name != name {
-                    if let Some(current_sound) = asset_manager.get_sound(&current_name) {
-                        stop_sound(current_sound);
-                    }
-                }
-            }
+    ) -> Result<(), AudioError> {
+        self.play_audio_at(asset_manager, name, category, None)
+    }
 
-            play_sound(
-                sound,
-                macroquad::audio::PlaySoundParams {
-                    looped: category == AudioCategory::Music,
-                    volume,
-                },
-            );
-            self.currently_playing
-                .insert(category, Some(name.to_string()));
-            println!("Playing audio: {}", name); // Debug print
-        } else {
-            println!("Audio not found: {}", name); // Debug print
+    // Like play_audio, but panned and attenuated by the emitter. Sounds loaded
+    // without pan copies are played centered.
+    pub fn play_audio_at(
+        &mut self,
+        asset_manager: &AssetManager,
+        name: &str,
+        category: AudioCategory,
+        emitter: Option<Emitter>,
+    ) -> Result<(), AudioError> {
+        let Some(sound) = Self::find_sound(asset_manager, name, emitter) else {
+            return Err(AudioError::NotLoaded(name.to_string()));
+        };
+        let gain = emitter.map_or(1.0, |e| e.gain(self.listener));
+
+        // Stop any currently playing audio in the same category. A sound that
+        // is already playing is restarted, since stopping a sound stops every
+        // playback of it.
+        let previous: Vec<Sound> = self
+            .voices
+            .iter()
+            .filter(|v| v.category == category)
+            .map(|v| v.sound.clone())
+            .collect();
+        for sound in &previous {
+            stop_sound(sound);
         }
+        self.voices.retain(|v| v.category != category);
+
+        let looped = category == AudioCategory::Music;
+        play_sound(
+            &sound,
+            macroquad::audio::PlaySoundParams {
+                looped,
+                volume: self.category_gain(category) * gain,
+            },
+        );
+        self.voices.push(Voice {
+            name: name.to_string(),
+            sound,
+            category,
+            started_at: get_time(),
+            duration: asset_manager.get_sound_duration(name),
+            looped,
+            fade: 1.0,
+            fade_rate: 0.0,
+            stop_when_silent: false,
+            emitter,
+            gain,
+        });
+        log::debug!(target: logging::AUDIO, "Playing audio: {}", name);
+        Ok(())
     }
 
-    pub fn stop_audio(&mut self, asset_manager: &AssetManager, category: &AudioCategory) {
-        if let Some(Some(current_name)) = self.currently_playing.get(category) {
-            if let Some(sound) = asset_manager.get_sound(current_name) {
-                stop_sound(sound);
-                self.currently_playing.insert(category.clone(), None);
+    // Plays a short sound without interrupting anything, even other copies of
+    // itself. Used for sounds that repeat quickly, like footsteps.
+    pub fn play_one_shot(
+        &mut self,
+        asset_manager: &AssetManager,
+        name: &str,
+        category: AudioCategory,
+        emitter: Option<Emitter>,
+    ) -> Result<(), AudioError> {
+        let Some(sound) = Self::find_sound(asset_manager, name, emitter) else {
+            return Err(AudioError::NotLoaded(name.to_string()));
+        };
+        let gain = emitter.map_or(1.0, |e| e.gain(self.listener));
+        play_sound(
+            &sound,
+            macroquad::audio::PlaySoundParams {
+                looped: false,
+                volume: self.category_gain(category) * gain,
+            },
+        );
+        // Clips of unknown length would never be dropped, so they are not tracked
+        let Some(duration) = asset_manager.get_sound_duration(name) else {
+            return Ok(());
+        };
+        self.voices.push(Voice {
+            name: name.to_string(),
+            sound,
+            category,
+            started_at: get_time(),
+            duration: Some(duration),
+            looped: false,
+            fade: 1.0,
+            fade_rate: 0.0,
+            stop_when_silent: false,
+            emitter,
+            gain,
+        });
+        Ok(())
+    }
+
+    // Starts a sound next to whatever else is playing in the category, fading it
+    // in over `fade_in` seconds. A voice of the same sound that is still fading
+    // out is faded back in instead of being restarted.
+    pub fn start_layer(
+        &mut self,
+        asset_manager: &AssetManager,
+        name: &str,
+        category: AudioCategory,
+        looped: bool,
+        fade_in: f32,
+        emitter: Option<Emitter>,
+    ) -> Result<(), AudioError> {
+        let fade_rate = fade_rate(fade_in);
+        if let Some(i) = self
+            .voices
+            .iter()
+            .position(|v| v.category == category && v.name == name)
+        {
+            let voice = &mut self.voices[i];
+            voice.fade_rate = fade_rate;
+            voice.stop_when_silent = false;
+            if fade_in <= 0.0 {
+                voice.fade = 1.0;
+                let voice = &self.voices[i];
+                let volume = self.category_gain(category) * voice.fade * voice.gain;
+                set_sound_volume(&voice.sound, volume);
             }
+            return Ok(());
         }
+
+        let Some(sound) = Self::find_sound(asset_manager, name, emitter) else {
+            return Err(AudioError::NotLoaded(name.to_string()));
+        };
+        let gain = emitter.map_or(1.0, |e| e.gain(self.listener));
+        let fade = if fade_in <= 0.0 { 1.0 } else { 0.0 };
+        play_sound(
+            &sound,
+            macroquad::audio::PlaySoundParams {
+                looped,
+                volume: self.category_gain(category) * fade * gain,
+            },
+        );
+        self.voices.push(Voice {
+            name: name.to_string(),
+            sound,
+            category,
+            started_at: get_time(),
+            duration: asset_manager.get_sound_duration(name),
+            looped,
+            fade,
+            fade_rate,
+            stop_when_silent: false,
+            emitter,
+            gain,
+        });
+        log::debug!(
+            target: logging::AUDIO,
+            "Fading in {} over {:.1}s",
+            name,
+            fade_in
+        );
+        Ok(())
     }
 
-    pub fn set_volume(&mut self, category: AudioCategory, volume: f32) {
-        let clamped_volume = volume.clamp(0.0, 1.0);
-        self.volume_levels.insert(category, clamped_volume);
-        // Note: We can't update the volume of currently playing sounds here
-        // because we don't have access to the AssetManager
+    // Fades a sound out over `seconds` and stops it once it is silent
+    pub fn fade_out(&mut self, category: AudioCategory, name: &str, seconds: f32) {
+        if seconds <= 0.0 {
+            self.stop(category, name);
+            return;
+        }
+        let rate = -fade_rate(seconds);
+        for voice in self
+            .voices
+            .iter_mut()
+            .filter(|v| v.category == category && v.name == name)
+        {
+            voice.fade_rate = rate;
+            voice.stop_when_silent = true;
+        }
     }
 
-    pub fn get_volume(&self, category: &AudioCategory) -> f32 {
-        *self.volume_levels.get(category).unwrap_or(&1.0)
+    // Sounds in the category that are playing and not on their way out
+    pub fn active_sounds(&self, category: AudioCategory) -> Vec<String> {
+        self.voices
+            .iter()
+            .filter(|v| v.category == category && !v.stop_when_silent)
+            .map(|v| v.name.clone())
+            .collect()
     }
 
-    pub fn play_music(&mut self, asset_manager: &AssetManager, name: &str) {
-        self.play_audio(asset_manager, name, AudioCategory::Music);
+    // Seconds left until a non-looping sound ends, if its length is known
+    pub fn remaining_time(&self, category: AudioCategory, name: &str) -> Option<f32> {
+        let now = get_time();
+        self.voices
+            .iter()
+            .find(|v| v.category == category && v.name == name && !v.looped)
+            .and_then(|v| v.duration.map(|d| d - (now - v.started_at) as f32))
     }
 
-    pub fn stop_music(&mut self, asset_manager: &AssetManager) {
-        self.stop_audio(asset_manager, &AudioCategory::Music);
+    pub fn stop(&mut self, category: AudioCategory, name: &str) {
+        for voice in self
+            .voices
+            .iter()
+            .filter(|v| v.category == category && v.name == name)
+        {
+            stop_sound(&voice.sound);
+        }
+        self.voices
+            .retain(|v| v.category != category || v.name != name);
     }
 
-    pub fn toggle_mute(&mut self, asset_manager: &AssetManager) {
-        // Calculate new volume (toggle between 0.0 and 1.0)
-        let new_volume = if self.volume_levels.values().any(|&v| v > 0.0) {
-            0.0
+    pub fn is_playing(&self, category: AudioCategory, name: &str) -> bool {
+        self.voices
+            .iter()
+            .any(|v| v.category == category && v.name == name)
+    }
+
+    // Whether the sound is on its way out, stopping once its fade reaches silence
+    pub fn is_fading_out(&self, category: AudioCategory, name: &str) -> bool {
+        self.voices
+            .iter()
+            .any(|v| v.category == category && v.name == name && v.stop_when_silent)
+    }
+
+    pub fn is_category_playing(&self, category: AudioCategory) -> bool {
+        self.voices.iter().any(|v| v.category == category)
+    }
+
+    // Drops voices that have played to the end and moves the music ducking
+    // towards its target. Should be called once per frame.
+    pub fn update(&mut self, delta_time: f32) {
+        let now = get_time();
+        self.voices.retain(|v| !v.is_finished(now));
+        self.update_fades(delta_time);
+
+        let duck_target = if self.is_category_playing(AudioCategory::Dialog) {
+            audio::MUSIC_DUCK_VOLUME
         } else {
             1.0
         };
+        if self.music_duck != duck_target {
+            let step = audio::MUSIC_DUCK_SPEED * delta_time;
+            self.music_duck = if self.music_duck < duck_target {
+                (self.music_duck + step).min(duck_target)
+            } else {
+                (self.music_duck - step).max(duck_target)
+            };
+            self.apply_category_volume(AudioCategory::Music);
+        }
+    }
 
-        // Set new volume for all categories
-        for volume in self.volume_levels.values_mut() {
-            *volume = new_volume;
+    // Moves the point attenuated emitters are measured from, usually the
+    // active character
+    pub fn set_listener(&mut self, position: Vec2) {
+        if self.listener == position {
+            return;
         }
+        self.listener = position;
+        for i in 0..self.voices.len() {
+            let Some(emitter) = self.voices[i].emitter.filter(|e| e.attenuate) else {
+                continue;
+            };
+            self.voices[i].gain = emitter.gain(position);
+            let voice = &self.voices[i];
+            let volume = self.category_gain(voice.category) * voice.fade * voice.gain;
+            set_sound_volume(&voice.sound, volume);
+        }
+    }
 
-        // Apply new volume to all playing sounds
-        for (_, name) in self.currently_playing.iter() {
-            if let Some(name) = name {
-                if let Some(sound) = asset_manager.get_sound(name) {
-                    set_sound_volume(sound, new_volume);
-                }
+    pub fn set_volume(&mut self, bus: Bus, volume: f32) {
+        let volume = volume.clamp(0.0, 1.0);
+        self.bus_state_mut(bus).volume = volume;
+        self.apply_bus_volume(bus);
+    }
+
+    pub fn get_volume(&self, bus: Bus) -> f32 {
+        self.bus_state(bus).volume
+    }
+
+    pub fn set_muted(&mut self, bus: Bus, muted: bool) {
+        self.bus_state_mut(bus).muted = muted;
+        self.apply_bus_volume(bus);
+    }
+
+    #[cfg(any(debug_assertions, feature = "debug-tools"))]
+    pub fn toggle_mute(&mut self, bus: Bus) {
+        let muted = !self.is_muted(bus);
+        self.set_muted(bus, muted);
+    }
+
+    pub fn is_muted(&self, bus: Bus) -> bool {
+        self.bus_state(bus).muted
+    }
+
+    fn update_fades(&mut self, delta_time: f32) {
+        for i in 0..self.voices.len() {
+            let voice = &mut self.voices[i];
+            if voice.fade_rate == 0.0 {
+                continue;
+            }
+            voice.fade = (voice.fade + voice.fade_rate * delta_time).clamp(0.0, 1.0);
+            if voice.fade == 1.0 || (voice.fade == 0.0 && !voice.stop_when_silent) {
+                voice.fade_rate = 0.0;
             }
+            let voice = &self.voices[i];
+            let volume = self.category_gain(voice.category) * voice.fade * voice.gain;
+            set_sound_volume(&voice.sound, volume);
+        }
+
+        let silent: Vec<Sound> = self
+            .voices
+            .iter()
+            .filter(|v| v.stop_when_silent && v.fade == 0.0)
+            .map(|v| v.sound.clone())
+            .collect();
+        for sound in &silent {
+            stop_sound(sound);
         }
+        self.voices
+            .retain(|v| !(v.stop_when_silent && v.fade == 0.0));
     }
 
-    pub fn is_muted(&self) -> bool {
-        self.volume_levels.values().all(|&v| v == 0.0)
+    fn find_sound(
+        asset_manager: &AssetManager,
+        name: &str,
+        emitter: Option<Emitter>,
+    ) -> Option<Sound> {
+        emitter
+            .and_then(|e| asset_manager.get_panned_sound(name, e.pan()))
+            .or_else(|| asset_manager.get_sound(name))
     }
+
+    fn bus_state(&self, bus: Bus) -> &BusState {
+        match bus {
+            Bus::Master => &self.master,
+            Bus::Category(category) => &self.buses[&category],
+        }
+    }
+
+    fn bus_state_mut(&mut self, bus: Bus) -> &mut BusState {
+        match bus {
+            Bus::Master => &mut self.master,
+            Bus::Category(category) => self.buses.get_mut(&category).unwrap(),
+        }
+    }
+
+    fn category_gain(&self, category: AudioCategory) -> f32 {
+        let duck = if category == AudioCategory::Music {
+            self.music_duck
+        } else {
+            1.0
+        };
+        self.master.gain() * self.buses[&category].gain() * duck
+    }
+
+    fn apply_bus_volume(&self, bus: Bus) {
+        match bus {
+            Bus::Master => AudioCategory::ALL
+                .iter()
+                .for_each(|&category| self.apply_category_volume(category)),
+            Bus::Category(category) => self.apply_category_volume(category),
+        }
+    }
+
+    fn apply_category_volume(&self, category: AudioCategory) {
+        let gain = self.category_gain(category);
+        for voice in self.voices.iter().filter(|v| v.category == category) {
+            set_sound_volume(&voice.sound, gain * voice.fade * voice.gain);
+        }
+    }
+}
+
+// Zero length fades jump straight to their target and have no rate
+fn fade_rate(seconds: f32) -> f32 {
+    if seconds > 0.0 { 1.0 / seconds } else { 0.0 }
 }
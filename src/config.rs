@@ -1,4 +1,6 @@
 pub mod character {
+    use macroquad::prelude::Vec2;
+
     // Character rendering and interaction offsets
     pub const X_OFFSET: f32 = -4.0;
     pub const Y_OFFSET: f32 = -90.0;
@@ -8,11 +10,55 @@ pub mod character {
     pub const HEIGHT: f32 = 120.0;
 
     pub const INTERACTION_RANGE: f32 = 250.0;
+
+    // Where the gang is placed in a level that has just been loaded
+    pub const DEFAULT_SPAWN: Vec2 = Vec2::new(1000.0, 800.0);
+
+    // Characters without talk frames stretch their standing frame by up to
+    // this fraction of its height while speaking
+    pub const TALK_STRETCH: f32 = 0.03;
+    // Mouth movements per second when a clip has no loudness envelope
+    pub const TALK_FALLBACK_RATE: f32 = 8.0;
+
+    // Walk cycle frames (0..8) on which a foot touches the ground. Running
+    // uses the same cycle but with a step on every other frame.
+    pub const WALK_STEP_FRAMES: [usize; 2] = [0, 4];
+    pub const RUN_STEP_FRAMES: [usize; 4] = [0, 2, 4, 6];
+}
+
+pub mod npc {
+    pub const DEFAULT_SPEED: f32 = 200.0;
+    // Seconds before a patrolling NPC that found no path tries again
+    pub const RETRY_DELAY: f32 = 2.0;
+}
+
+pub mod guard {
+    use macroquad::prelude::Color;
+
+    pub const RANGE: f32 = 500.0;
+    // Degrees
+    pub const ANGLE: f32 = 70.0;
+    pub const DETECTION_TIME: f32 = 1.5;
+    // How much of the detection meter drains per second out of sight
+    pub const DECAY_RATE: f32 = 0.5;
+
+    // Meter drawn above a guard that is becoming suspicious
+    pub const METER_WIDTH: f32 = 80.0;
+    pub const METER_HEIGHT: f32 = 10.0;
+    pub const METER_BACKGROUND: Color = Color::new(0.0, 0.0, 0.0, 0.6);
+    pub const METER_COLOR: Color = Color::new(1.0, 0.8, 0.0, 1.0);
+    pub const METER_FULL_COLOR: Color = Color::new(1.0, 0.2, 0.1, 1.0);
+    // Debug overlay of the vision cones
+    pub const CONE_COLOR: Color = Color::new(1.0, 1.0, 0.0, 0.2);
 }
 
 pub mod inventory {
+    use macroquad::prelude::{Color, WHITE};
+
     pub const START_X: f32 = 120.0;
     pub const START_Y: f32 = 1280.0;
+    // Top of the open inventory bar, clicks below it stay in the inventory
+    pub const TOP_Y: f32 = START_Y - 59.0;
     pub const SLOT_SIZE: f32 = 150.0;
     pub const SLOT_SPACING: f32 = 0.0;
     pub const SLOT_COUNT: usize = 9;
@@ -21,11 +67,31 @@ pub mod inventory {
     pub const RIGHT_ARROW_OFFSET_X: f32 = 10.0; // Offset from the right edge of the last slot
     pub const ARROW_OFFSET_Y: f32 = 20.0; // Vertical offset from the slots (0 means aligned with slots)
     pub const ARROW_SIZE: f32 = 50.0; // Size of the arrow buttons
+
+    pub const BUTTON_FRAMES: usize = 13;
+    pub const LEFT_ARROW_TEXTURE: &str = "Huvudmeny/inventory/pilv-271.png";
+    pub const RIGHT_ARROW_TEXTURE: &str = "Huvudmeny/inventory/pilh-272.png";
+    pub const OPEN_SOUND: &str = "Huvudmeny/ljudfx/oppna.wav";
+    pub const CLOSE_SOUND: &str = "Huvudmeny/ljudfx/stanga.wav";
+    // Played when a recipe has no sound of its own
+    pub const COMBINE_SOUND: &str = OPEN_SOUND;
+    // How long an item made by combining others stays highlighted, in seconds
+    pub const COMBINE_FLASH_TIME: f32 = 0.6;
+    pub const COMBINE_COLOR: Color = Color::new(1.0, 0.9, 0.4, 0.6);
+
+    // Cursor movement after pressing on an item before it counts as a drag
+    pub const DRAG_THRESHOLD: f32 = 12.0;
+    // Name panel shown above the hovered slot
+    pub const PANEL_PADDING: f32 = 10.0;
+    pub const PANEL_MARGIN: f32 = 10.0;
+    pub const PANEL_FONT_SIZE: f32 = 36.0;
+    pub const PANEL_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.7);
+    pub const PANEL_TEXT_COLOR: Color = WHITE;
 }
 
 pub mod dialog {
     use macroquad::prelude::Color;
-    use macroquad::prelude::{DARKGRAY, GRAY, GREEN, RED, WHITE, YELLOW};
+    use macroquad::prelude::{GREEN, RED, WHITE, YELLOW};
 
     pub const WIDTH: f32 = 1920.0;
     pub const HEIGHT: f32 = 258.0;
@@ -44,3 +110,264 @@ pub mod dialog {
     pub const OPTION_BOX_COLOR: Color = GREEN;
     pub const OPTION_HOVER_BOX_COLOR: Color = RED;
 }
+
+pub mod paths {
+    pub const APP_DIR_NAME: &str = "openjonsson";
+    pub const DEFAULT_ROOT: &str = "static";
+    pub const RESOURCE_DIR: &str = "resources";
+
+    // Extra search roots can be given on the command line (repeatable),
+    // through the environment (a PATH-style list) or in `paths.json`
+    // inside the user config directory: { "data_dirs": ["/path/to/root"] }
+    pub const DATA_DIR_FLAG: &str = "--data-dir";
+    pub const DATA_DIR_ENV: &str = "OPENJONSSON_DATA_DIR";
+    pub const PATHS_CONFIG_FILE: &str = "paths.json";
+    // User settings, also in the user config directory
+    pub const SETTINGS_FILE: &str = "settings.json";
+    // Translations, `lang/<language>.json` next to level_data.json
+    pub const LANGUAGE_DIR: &str = "lang";
+
+    // Written to the working directory after the startup asset audit
+    pub const MISSING_ASSETS_REPORT: &str = "missing_assets.txt";
+}
+
+pub mod cursor {
+    // Virtual cursor speed in game pixels per second, at full stick tilt
+    pub const SPEED: f32 = 900.0;
+    // Stick tilt below this is ignored
+    #[cfg(feature = "gamepad")]
+    pub const STICK_DEADZONE: f32 = 0.2;
+    // Mouse movement smaller than this doesn't take the cursor back from the keyboard
+    pub const MOUSE_THRESHOLD: f32 = 0.5;
+}
+
+pub mod hotspots {
+    use macroquad::prelude::Color;
+
+    pub const OUTLINE_THICKNESS: f32 = 3.0;
+    pub const MARKER_RADIUS: f32 = 12.0;
+    pub const FONT_SIZE: f32 = 36.0;
+    pub const LABEL_PADDING: f32 = 8.0;
+    pub const COLOR: Color = Color::new(1.0, 0.85, 0.2, 0.9);
+    // Items the active character can't reach yet
+    pub const OUT_OF_RANGE_COLOR: Color = Color::new(0.6, 0.6, 0.6, 0.6);
+    pub const LABEL_BACKGROUND_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.7);
+}
+
+pub mod hover {
+    use macroquad::prelude::{Color, WHITE};
+
+    pub const FONT_SIZE: f32 = 36.0;
+    // Label position relative to the cursor hotspot
+    pub const OFFSET_X: f32 = 40.0;
+    pub const OFFSET_Y: f32 = -20.0;
+    pub const PADDING: f32 = 8.0;
+    pub const TEXT_COLOR: Color = WHITE;
+    pub const BACKGROUND_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.7);
+}
+
+pub mod subtitles {
+    use macroquad::prelude::{Color, WHITE};
+
+    pub const FONT_SIZE: f32 = 44.0;
+    // Baseline of the last line, above the inventory and dialog options
+    pub const BOTTOM_Y: f32 = 1150.0;
+    pub const MAX_WIDTH: f32 = 1500.0;
+    pub const LINE_SPACING: f32 = 54.0;
+    pub const PADDING: f32 = 12.0;
+    pub const TEXT_COLOR: Color = WHITE;
+    pub const BACKGROUND_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.6);
+}
+
+pub mod video {
+    // Frames loaded ahead of the one on screen
+    pub const PRELOAD_FRAMES: u32 = 4;
+}
+
+pub mod sequence {
+    use macroquad::prelude::{BLACK, Color};
+
+    pub const FADE_COLOR: Color = BLACK;
+}
+
+pub mod strings {
+    // Language of the built-in strings and of the names in level_data.json
+    pub const BUILT_IN_LANGUAGE: &str = "sv";
+
+    pub const DEFAULTS: &[(&str, &str)] = &[
+        ("hover.take", "Ta {object}"),
+        ("hover.walk_to", "Gå till {object}"),
+        ("hover.talk_to", "Prata med {object}"),
+        ("hover.go_to", "Gå till {object}"),
+        ("hover.look_at", "Titta på {object}"),
+        ("hover.select", "Välj {object}"),
+        ("hover.character", "{object}"),
+        ("inventory.cannot_use", "Det går inte."),
+        ("inventory.cannot_carry", "Det kan jag inte bära."),
+        ("inventory.full", "Jag kan inte bära mer."),
+        ("inventory.too_far", "Jag står för långt bort."),
+        ("inventory.cannot_place", "Där kan jag inte lägga den."),
+        ("inventory.cannot_combine", "Det klarar jag inte."),
+        ("guard.caught", "Stopp där!"),
+        ("menu.new_game", "Nytt spel"),
+        ("menu.continue", "Fortsätt"),
+        ("menu.options", "Inställningar"),
+        ("menu.quit", "Avsluta"),
+        ("menu.resume", "Återgå till spelet"),
+        ("menu.return_to_title", "Huvudmeny"),
+        ("menu.volume_master", "Huvudvolym"),
+        ("menu.volume_music", "Musik"),
+        ("menu.volume_ambience", "Miljöljud"),
+        ("menu.volume_dialog", "Dialog"),
+        ("menu.volume_sfx", "Ljudeffekter"),
+        ("menu.fullscreen", "Helskärm"),
+        ("menu.back", "Tillbaka"),
+        ("menu.on", "på"),
+        ("menu.off", "av"),
+    ];
+}
+
+pub mod menu {
+    use macroquad::prelude::Color;
+    use macroquad::prelude::{GRAY, WHITE, YELLOW};
+
+    // Layout for buttons that have no MenuItem in level_data.json
+    pub const BUTTON_WIDTH: f32 = 700.0;
+    pub const BUTTON_HEIGHT: f32 = 80.0;
+    pub const BUTTON_SPACING: f32 = 100.0;
+    pub const START_Y: f32 = 420.0;
+    pub const FONT_SIZE: f32 = 50.0;
+
+    pub const TEXT_COLOR: Color = WHITE;
+    pub const SELECTED_TEXT_COLOR: Color = YELLOW;
+    pub const DISABLED_TEXT_COLOR: Color = GRAY;
+    pub const BUTTON_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.6);
+    // Drawn over the paused game
+    pub const PAUSE_OVERLAY_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.5);
+}
+
+pub mod settings {
+    pub const DEFAULT_LANGUAGE: &str = "sv";
+    pub const DEFAULT_ANIMATION_SPEED: f32 = 0.1;
+    pub const MIN_ANIMATION_SPEED: f32 = 0.02;
+    #[cfg(any(debug_assertions, feature = "debug-tools"))]
+    pub const ANIMATION_SPEED_STEP: f32 = 0.01;
+
+    // Action name and key name, see input::Action and settings::key_from_name
+    pub const DEFAULT_KEY_BINDINGS: &[(&str, &str)] = &[
+        ("pause", "Escape"),
+        ("skip_line", "Period"),
+        ("skip_sequence", "Backspace"),
+        ("toggle_fullscreen", "F11"),
+        ("toggle_inventory", "I"),
+        ("select_character_1", "Key1"),
+        ("select_character_2", "Key2"),
+        ("select_character_3", "Key3"),
+        ("select_character_4", "Key4"),
+        ("menu_up", "Up"),
+        ("menu_down", "Down"),
+        ("menu_left", "Left"),
+        ("menu_right", "Right"),
+        ("menu_confirm", "Enter"),
+        ("cursor_up", "Up"),
+        ("cursor_down", "Down"),
+        ("cursor_left", "Left"),
+        ("cursor_right", "Right"),
+        ("cursor_click", "Space"),
+        ("cursor_right_click", "X"),
+        ("next_hotspot", "E"),
+        ("previous_hotspot", "Q"),
+        ("reveal_hotspots", "H"),
+    ];
+
+    // Action name and gilrs `Button` name, see gamepad::button_from_name
+    #[cfg(feature = "gamepad")]
+    pub const DEFAULT_GAMEPAD_BINDINGS: &[(&str, &str)] = &[
+        ("pause", "Start"),
+        ("skip_line", "West"),
+        ("skip_sequence", "Select"),
+        ("toggle_inventory", "North"),
+        ("menu_up", "DPadUp"),
+        ("menu_down", "DPadDown"),
+        ("menu_left", "DPadLeft"),
+        ("menu_right", "DPadRight"),
+        ("menu_confirm", "South"),
+        ("cursor_click", "South"),
+        ("cursor_right_click", "East"),
+        ("next_hotspot", "RightTrigger"),
+        ("previous_hotspot", "LeftTrigger"),
+        ("reveal_hotspots", "RightTrigger2"),
+    ];
+
+    // Only available in debug builds or with the `debug-tools` feature
+    #[cfg(any(debug_assertions, feature = "debug-tools"))]
+    pub const DEBUG_KEY_BINDINGS: &[(&str, &str)] = &[
+        ("toggle_debug", "D"),
+        ("toggle_grid", "G"),
+        ("toggle_missing_assets", "A"),
+        ("toggle_log", "O"),
+        ("next_log_target", "Tab"),
+        ("log_more_verbose", "Equal"),
+        ("log_less_verbose", "Minus"),
+        ("mute_master", "M"),
+        ("mute_music", "F5"),
+        ("mute_dialog", "F6"),
+        ("mute_sound_effects", "F7"),
+        ("mute_ambience", "F8"),
+        ("volume_down", "LeftBracket"),
+        ("volume_up", "RightBracket"),
+        ("toggle_instant_move", "F3"),
+        ("toggle_bounding_boxes", "B"),
+        ("animation_faster", "PageUp"),
+        ("animation_slower", "PageDown"),
+        ("toggle_level_switch", "L"),
+        ("switch_level_0", "Key0"),
+        ("switch_level_1", "Key1"),
+        ("switch_level_2", "Key2"),
+        ("switch_level_3", "Key3"),
+        ("switch_level_4", "Key4"),
+        ("switch_level_5", "Key5"),
+        ("switch_level_6", "Key6"),
+        ("switch_level_7", "Key7"),
+        ("switch_level_8", "Key8"),
+        ("switch_level_9", "Key9"),
+    ];
+}
+
+pub mod voice {
+    // Length assumed for voice clips whose duration could not be read
+    pub const UNKNOWN_LINE_LENGTH: f32 = 3.0;
+    // Lines without a clip stay up for a time based on the length of their text,
+    // scaled by the text speed setting
+    pub const TEXT_SECONDS_PER_CHAR: f32 = 0.06;
+    pub const TEXT_MIN_LENGTH: f32 = 1.5;
+
+    // Samples per second of the loudness envelope that drives talk animations
+    pub const ENVELOPE_RATE: usize = 30;
+    // Clips longer than this, in seconds, are not analyzed
+    pub const ENVELOPE_MAX_LENGTH: f32 = 30.0;
+}
+
+pub mod audio {
+    // Music bus gain while a dialog line is playing
+    pub const MUSIC_DUCK_VOLUME: f32 = 0.35;
+    // How fast the ducking gain moves, in gain units per second
+    pub const MUSIC_DUCK_SPEED: f32 = 3.0;
+    // Master volume step for the debug volume keys
+    pub const VOLUME_STEP: f32 = 0.1;
+    // Default crossfade between music tracks, in seconds
+    pub const MUSIC_CROSSFADE: f32 = 2.0;
+    // Fade in and out time for ambient layers, in seconds
+    pub const AMBIENT_FADE: f32 = 1.5;
+
+    // Number of stereo positions from left to right. A panned copy of a clip
+    // is rendered when it plays, so these only cost time, not memory.
+    pub const PAN_STEPS: usize = 9;
+    // How far towards one speaker a sound at the screen edge is panned, 0..1
+    pub const PAN_WIDTH: f32 = 0.8;
+    // Attenuated emitters are at full volume up to START pixels from the
+    // listener and fade down to MIN_GAIN at END
+    pub const ATTENUATION_START: f32 = 200.0;
+    pub const ATTENUATION_END: f32 = 1400.0;
+    pub const ATTENUATION_MIN_GAIN: f32 = 0.2;
+}
@@ -0,0 +1,80 @@
+use crate::config::cursor;
+use macroquad::prelude::Vec2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Snap {
+    Next,
+    Previous,
+}
+
+// Cursor position in game coordinates. It follows the mouse whenever the mouse
+// moves, and can otherwise be moved with the keyboard or a gamepad stick or
+// snapped between hotspots, so the game can be played without a mouse.
+pub struct VirtualCursor {
+    pub position: Vec2,
+    // The cursor changed position this frame
+    pub moved: bool,
+    last_mouse: Vec2,
+}
+
+impl VirtualCursor {
+    pub fn new(mouse_pos: Vec2) -> Self {
+        VirtualCursor {
+            position: mouse_pos,
+            moved: false,
+            last_mouse: mouse_pos,
+        }
+    }
+
+    // `direction` has a length of at most 1, `hotspots` are the points snapping
+    // cycles through, in any order
+    pub fn update(
+        &mut self,
+        mouse_pos: Vec2,
+        direction: Vec2,
+        snap: Option<Snap>,
+        hotspots: &[Vec2],
+        delta_time: f32,
+    ) {
+        let previous = self.position;
+
+        if mouse_pos.distance(self.last_mouse) > cursor::MOUSE_THRESHOLD {
+            self.position = mouse_pos;
+        }
+        self.last_mouse = mouse_pos;
+
+        self.position += direction * cursor::SPEED * delta_time;
+        if let Some(snap) = snap
+            && let Some(hotspot) = Self::snap_target(self.position, snap, hotspots)
+        {
+            self.position = hotspot;
+        }
+        self.position = self
+            .position
+            .clamp(Vec2::ZERO, Vec2::new(1920.0 - 1.0, 1440.0 - 1.0));
+        self.moved = self.position != previous;
+    }
+
+    // Hotspots are visited left to right, then top to bottom, wrapping around.
+    // Working from the cursor position rather than an index keeps the order
+    // sensible when hotspots appear or disappear.
+    fn snap_target(position: Vec2, snap: Snap, hotspots: &[Vec2]) -> Option<Vec2> {
+        let key = |p: Vec2| (p.x, p.y);
+        let mut sorted = hotspots.to_vec();
+        sorted.sort_by(|a, b| key(*a).partial_cmp(&key(*b)).unwrap());
+
+        match snap {
+            Snap::Next => sorted
+                .iter()
+                .copied()
+                .find(|&p| key(p) > key(position))
+                .or(sorted.first().copied()),
+            Snap::Previous => sorted
+                .iter()
+                .rev()
+                .copied()
+                .find(|&p| key(p) < key(position))
+                .or(sorted.last().copied()),
+        }
+    }
+}
@@ -10,6 +10,8 @@ pub struct Dialog {
     pub description: String,
     pub open_audio: Option<String>,
     pub tree: Vec<DialogNode>,
+    #[serde(default)]
+    pub descriptions: crate::Descriptions,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -24,7 +26,22 @@ pub struct DialogOption {
     pub id: u32,
     pub text: String,
     pub response_audio: Vec<String>,
+    // Who says each response clip, by index. Clips without an entry are said
+    // by the dialog's character.
+    #[serde(default)]
+    pub response_speakers: Vec<String>,
     pub target: u32,
+    // Name of a sequence of the scene to play after the response
+    #[serde(default)]
+    pub sequence: Option<String>,
+}
+
+impl DialogOption {
+    pub fn response_speaker<'a>(&'a self, index: usize, dialog: &'a Dialog) -> &'a str {
+        self.response_speakers
+            .get(index)
+            .unwrap_or(&dialog.description)
+    }
 }
 
 pub struct DialogMenu {
@@ -32,6 +49,10 @@ pub struct DialogMenu {
     pub current_dialog_id: Option<u32>,
     pub current_level: usize,
     pub hovered_option: Option<usize>,
+    // Set when an option ends the dialog, which closes once its lines are said
+    pub close_after_lines: bool,
+    // Sequence of the chosen option, started once its lines are said
+    pub sequence_after_lines: Option<String>,
 }
 
 impl DialogMenu {
@@ -41,6 +62,8 @@ impl DialogMenu {
             current_dialog_id: None,
             current_level: 0,
             hovered_option: None,
+            close_after_lines: false,
+            sequence_after_lines: None,
         }
     }
 }
@@ -0,0 +1,182 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum AssetError {
+    NotFound { path: String, searched: Vec<String> },
+    Texture { path: String, message: String },
+    Sound { path: String, message: String },
+    Font { name: String, message: String },
+    Data { path: String, message: String },
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::NotFound { path, searched } => {
+                write!(f, "{} not found, searched roots:", path)?;
+                if searched.is_empty() {
+                    write!(f, " (none)")?;
+                }
+                for root in searched {
+                    write!(f, "\n  {}", root)?;
+                }
+                Ok(())
+            }
+            AssetError::Texture { path, message } => {
+                write!(f, "Failed to load texture {}: {}", path, message)
+            }
+            AssetError::Sound { path, message } => {
+                write!(f, "Failed to load sound {}: {}", path, message)
+            }
+            AssetError::Font { name, message } => {
+                write!(f, "Failed to load font {}: {}", name, message)
+            }
+            AssetError::Data { path, message } => write!(f, "Failed to load {}: {}", path, message),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+#[derive(Debug)]
+pub enum DataError {
+    Load(AssetError),
+    Parse {
+        file: String,
+        // Location of the offending value, e.g. `levels[0].scenes[3].items[1].x`
+        json_path: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl DataError {
+    pub fn from_json(file: &str, error: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        let json_path = error.path().to_string();
+        let inner = error.into_inner();
+        DataError::Parse {
+            file: file.to_string(),
+            json_path,
+            line: inner.line(),
+            column: inner.column(),
+            message: inner.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataError::Load(e) => write!(f, "{}", e),
+            DataError::Parse {
+                file,
+                json_path,
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "Failed to parse {} at {} (line {}, column {}): {}",
+                file, json_path, line, column, message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DataError {}
+
+impl From<AssetError> for DataError {
+    fn from(e: AssetError) -> Self {
+        DataError::Load(e)
+    }
+}
+
+#[derive(Debug)]
+pub enum AudioError {
+    NotLoaded(String),
+}
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioError::NotLoaded(name) => write!(f, "Audio not loaded: {}", name),
+        }
+    }
+}
+
+impl std::error::Error for AudioError {}
+
+#[derive(Debug)]
+pub enum GridError {
+    NoWalkableNode { near: (i32, i32) },
+    NoPath { start: (i32, i32), goal: (i32, i32) },
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GridError::NoWalkableNode { near } => {
+                write!(f, "No walkable node near {},{}", near.0, near.1)
+            }
+            GridError::NoPath { start, goal } => write!(
+                f,
+                "No path from {},{} to {},{}",
+                start.0, start.1, goal.0, goal.1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GridError {}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io { path: String, message: String },
+    Serialize { message: String },
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io { path, message } => {
+                write!(f, "Failed to save settings to {}: {}", path, message)
+            }
+            SettingsError::Serialize { message } => {
+                write!(f, "Failed to serialize settings: {}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+// Errors that prevent the game from starting
+#[derive(Debug)]
+pub enum GameError {
+    Asset(AssetError),
+    Data(DataError),
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::Asset(e) => write!(f, "{}", e),
+            GameError::Data(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+impl From<AssetError> for GameError {
+    fn from(e: AssetError) -> Self {
+        GameError::Asset(e)
+    }
+}
+
+impl From<DataError> for GameError {
+    fn from(e: DataError) -> Self {
+        GameError::Data(e)
+    }
+}
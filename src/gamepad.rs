@@ -0,0 +1,110 @@
+use crate::config::cursor;
+use crate::logging;
+use gilrs::{Axis, Button, EventType, Gilrs};
+use macroquad::prelude::Vec2;
+use std::collections::HashSet;
+
+// Buttons pressed on any connected gamepad this frame, and the left stick of the
+// first one that has it tilted
+pub struct Gamepads {
+    // None when no gamepad backend is available on this platform
+    gilrs: Option<Gilrs>,
+    pressed: HashSet<Button>,
+    stick: Vec2,
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(e) => {
+                log::warn!(target: logging::INPUT, "Gamepads are not available: {}", e);
+                None
+            }
+        };
+        Gamepads {
+            gilrs,
+            pressed: HashSet::new(),
+            stick: Vec2::ZERO,
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.pressed.clear();
+        self.stick = Vec2::ZERO;
+        let Some(gilrs) = &mut self.gilrs else {
+            return;
+        };
+
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                EventType::ButtonPressed(button, _) => {
+                    self.pressed.insert(button);
+                }
+                EventType::Connected => {
+                    log::info!(
+                        target: logging::INPUT,
+                        "Gamepad connected: {}",
+                        gilrs.gamepad(event.id).name()
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        for (_, gamepad) in gilrs.gamepads() {
+            // The stick's y axis points up, the game's down
+            let stick = Vec2::new(
+                gamepad.value(Axis::LeftStickX),
+                -gamepad.value(Axis::LeftStickY),
+            );
+            if stick.length() > cursor::STICK_DEADZONE {
+                self.stick = stick.clamp_length_max(1.0);
+                break;
+            }
+        }
+    }
+
+    pub fn is_pressed(&self, button: Button) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    pub fn is_down(&self, button: Button) -> bool {
+        self.gilrs.as_ref().is_some_and(|gilrs| {
+            gilrs
+                .gamepads()
+                .any(|(_, gamepad)| gamepad.is_pressed(button))
+        })
+    }
+
+    pub fn stick(&self) -> Vec2 {
+        self.stick
+    }
+}
+
+// Button names match gilrs' `Button` variants, e.g. "South", "Start", "DPadUp"
+pub fn button_from_name(name: &str) -> Option<Button> {
+    const BUTTONS: &[Button] = &[
+        Button::South,
+        Button::East,
+        Button::North,
+        Button::West,
+        Button::LeftTrigger,
+        Button::LeftTrigger2,
+        Button::RightTrigger,
+        Button::RightTrigger2,
+        Button::Select,
+        Button::Start,
+        Button::Mode,
+        Button::LeftThumb,
+        Button::RightThumb,
+        Button::DPadUp,
+        Button::DPadDown,
+        Button::DPadLeft,
+        Button::DPadRight,
+    ];
+    BUTTONS
+        .iter()
+        .copied()
+        .find(|button| format!("{:?}", button).eq_ignore_ascii_case(name))
+}
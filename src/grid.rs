@@ -1,4 +1,6 @@
+use crate::error::GridError;
 use macroquad::prelude::*;
+use serde::Deserialize;
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap, HashSet};
 
@@ -21,12 +23,43 @@ impl PartialOrd for Node {
     }
 }
 
+// What the floor is made of, which decides the footstep sounds
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Surface {
+    Wood,
+    Stone,
+    Carpet,
+}
+
+// Surface tags for the nodes of one scene. Untagged nodes use the default.
+#[derive(Clone, Debug, Default)]
+pub struct SurfaceMap {
+    nodes: HashMap<(i32, i32), Surface>,
+    default: Option<Surface>,
+}
+
+impl SurfaceMap {
+    pub fn new(tagged: &HashMap<Surface, Vec<(i32, i32)>>, default: Option<Surface>) -> Self {
+        let nodes = tagged
+            .iter()
+            .flat_map(|(&surface, nodes)| nodes.iter().map(move |&node| (node, surface)))
+            .collect();
+        SurfaceMap { nodes, default }
+    }
+
+    pub fn surface_at(&self, node: (i32, i32)) -> Option<Surface> {
+        self.nodes.get(&node).copied().or(self.default)
+    }
+}
+
 pub struct Grid {
     a: f32,
     m: f32,
     stretch: (f32, f32),
     grid_offset: f32,
     pub blocked_nodes: HashSet<(i32, i32)>,
+    pub surfaces: SurfaceMap,
 }
 
 // The original game uses a grid system to determine the position of the characters and
@@ -41,6 +74,7 @@ impl Grid {
             stretch: (38.81, 10.32),
             grid_offset: 10.,
             blocked_nodes: HashSet::new(),
+            surfaces: SurfaceMap::default(),
         }
     }
 
@@ -70,6 +104,39 @@ impl Grid {
         self.blocked_nodes = blocked_nodes.into_iter().collect();
     }
 
+    pub fn update_surfaces(&mut self, surfaces: SurfaceMap) {
+        self.surfaces = surfaces;
+    }
+
+    pub fn surface_at(&self, node: (i32, i32)) -> Option<Surface> {
+        self.surfaces.surface_at(node)
+    }
+
+    // Walks the nodes on a straight line between two nodes and checks that
+    // none of them is blocked. The end nodes themselves don't count, since a
+    // character can stand next to a wall.
+    pub fn has_line_of_sight(&self, from: (i32, i32), to: (i32, i32)) -> bool {
+        let (dx, dy) = ((to.0 - from.0).abs(), -(to.1 - from.1).abs());
+        let (sx, sy) = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+        let mut error = dx + dy;
+        let mut node = from;
+        while node != to {
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                node.0 += sx;
+            }
+            if doubled <= dx {
+                error += dx;
+                node.1 += sy;
+            }
+            if node != to && self.blocked_nodes.contains(&node) {
+                return false;
+            }
+        }
+        true
+    }
+
     pub fn is_node_walkable(&self, node: (i32, i32)) -> bool {
         let (x, y) = node;
 
@@ -107,7 +174,11 @@ impl Grid {
         blocked_diagonals
     }
 
-    pub fn pathfind(&self, start: (i32, i32), goal: (i32, i32)) -> Option<Vec<(i32, i32)>> {
+    pub fn pathfind(
+        &self,
+        start: (i32, i32),
+        goal: (i32, i32),
+    ) -> Result<Vec<(i32, i32)>, GridError> {
         let mut open_set = BinaryHeap::new();
         let mut came_from = HashMap::new();
         let mut g_score = HashMap::new();
@@ -123,7 +194,7 @@ impl Grid {
 
         while let Some(current) = open_set.pop() {
             if current.position == goal {
-                return Some(self.reconstruct_path(came_from, current.position));
+                return Ok(self.reconstruct_path(came_from, current.position));
             }
 
             for neighbor in self.get_neighbors(current.position) {
@@ -143,7 +214,38 @@ impl Grid {
             }
         }
 
-        None
+        Err(GridError::NoPath { start, goal })
+    }
+
+    // Walkable nodes within `search_radius` of `target`, closest first
+    pub fn closest_walkable_nodes(
+        &self,
+        target: (i32, i32),
+        n: usize,
+        search_radius: i32,
+    ) -> Result<Vec<(i32, i32)>, GridError> {
+        let mut walkable_nodes = Vec::new();
+
+        for dx in -search_radius..=search_radius {
+            for dy in -search_radius..=search_radius {
+                let node = (target.0 + dx, target.1 + dy);
+
+                if self.is_node_walkable(node) {
+                    walkable_nodes.push((node, dx * dx + dy * dy));
+                }
+            }
+        }
+
+        if walkable_nodes.is_empty() {
+            return Err(GridError::NoWalkableNode { near: target });
+        }
+
+        walkable_nodes.sort_by_key(|&(_, distance)| distance);
+        Ok(walkable_nodes
+            .into_iter()
+            .take(n)
+            .map(|(node, _)| node)
+            .collect())
     }
 
     fn get_neighbors(&self, pos: (i32, i32)) -> Vec<(i32, i32)> {
@@ -188,3 +290,45 @@ impl Grid {
         path
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid_with_blocked(nodes: &[(i32, i32)]) -> Grid {
+        let mut grid = Grid::new();
+        grid.update_blocked_nodes(nodes.to_vec());
+        grid
+    }
+
+    #[test]
+    fn open_grid_has_line_of_sight() {
+        let grid = Grid::new();
+        assert!(grid.has_line_of_sight((5, 5), (15, 9)));
+        assert!(grid.has_line_of_sight((15, 9), (5, 5)));
+        assert!(grid.has_line_of_sight((5, 5), (5, 5)));
+    }
+
+    #[test]
+    fn blocked_node_on_a_straight_line() {
+        let grid = grid_with_blocked(&[(10, 5)]);
+        assert!(!grid.has_line_of_sight((5, 5), (15, 5)));
+        assert!(!grid.has_line_of_sight((15, 5), (5, 5)));
+        assert!(grid.has_line_of_sight((5, 6), (15, 6)));
+    }
+
+    #[test]
+    fn blocked_node_on_a_diagonal() {
+        let grid = grid_with_blocked(&[(8, 8)]);
+        assert!(!grid.has_line_of_sight((5, 5), (11, 11)));
+        assert!(!grid.has_line_of_sight((11, 11), (5, 5)));
+        assert!(grid.has_line_of_sight((5, 6), (11, 12)));
+    }
+
+    #[test]
+    fn end_nodes_do_not_block() {
+        let grid = grid_with_blocked(&[(5, 5), (10, 5)]);
+        assert!(grid.has_line_of_sight((5, 5), (9, 5)));
+        assert!(grid.has_line_of_sight((9, 5), (10, 5)));
+    }
+}
@@ -0,0 +1,294 @@
+#[cfg(any(debug_assertions, feature = "debug-tools"))]
+use crate::audio::{AudioCategory, Bus};
+use crate::config::settings;
+#[cfg(feature = "gamepad")]
+use crate::gamepad::{Gamepads, button_from_name};
+use crate::logging;
+use crate::settings::{Settings, key_from_name};
+#[cfg(feature = "gamepad")]
+use gilrs::Button;
+use macroquad::prelude::{KeyCode, Vec2, is_key_down, is_key_pressed};
+use std::collections::HashMap;
+
+// Something the player can do from the keyboard. Each action is bound to a key by
+// name in the settings, e.g. "toggle_inventory": "I".
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Pause,
+    SkipLine,
+    SkipSequence,
+    ToggleFullscreen,
+    ToggleInventory,
+    // Index into the characters, named from 1 ("select_character_1")
+    SelectCharacter(usize),
+    MenuUp,
+    MenuDown,
+    MenuLeft,
+    MenuRight,
+    MenuConfirm,
+    CursorUp,
+    CursorDown,
+    CursorLeft,
+    CursorRight,
+    CursorClick,
+    CursorRightClick,
+    NextHotspot,
+    PreviousHotspot,
+    RevealHotspots,
+    #[cfg(any(debug_assertions, feature = "debug-tools"))]
+    Debug(DebugAction),
+}
+
+#[cfg(any(debug_assertions, feature = "debug-tools"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DebugAction {
+    ToggleTools,
+    ToggleGrid,
+    ToggleMissingAssets,
+    ToggleLog,
+    NextLogTarget,
+    LogMoreVerbose,
+    LogLessVerbose,
+    ToggleMute(Bus),
+    VolumeDown,
+    VolumeUp,
+    ToggleInstantMove,
+    ToggleBoundingBoxes,
+    AnimationFaster,
+    AnimationSlower,
+    ToggleLevelSwitch,
+    SwitchLevel(u32),
+}
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Action> {
+        let action = match name {
+            "pause" => Action::Pause,
+            "skip_line" => Action::SkipLine,
+            "skip_sequence" => Action::SkipSequence,
+            "toggle_fullscreen" => Action::ToggleFullscreen,
+            "toggle_inventory" => Action::ToggleInventory,
+            "menu_up" => Action::MenuUp,
+            "menu_down" => Action::MenuDown,
+            "menu_left" => Action::MenuLeft,
+            "menu_right" => Action::MenuRight,
+            "menu_confirm" => Action::MenuConfirm,
+            "cursor_up" => Action::CursorUp,
+            "cursor_down" => Action::CursorDown,
+            "cursor_left" => Action::CursorLeft,
+            "cursor_right" => Action::CursorRight,
+            "cursor_click" => Action::CursorClick,
+            "cursor_right_click" => Action::CursorRightClick,
+            "next_hotspot" => Action::NextHotspot,
+            "previous_hotspot" => Action::PreviousHotspot,
+            "reveal_hotspots" => Action::RevealHotspots,
+            _ => {
+                if let Some(number) = name.strip_prefix("select_character_") {
+                    let number = number.parse::<usize>().ok().filter(|&n| n > 0)?;
+                    return Some(Action::SelectCharacter(number - 1));
+                }
+                #[cfg(any(debug_assertions, feature = "debug-tools"))]
+                return DebugAction::from_name(name).map(Action::Debug);
+                #[cfg(not(any(debug_assertions, feature = "debug-tools")))]
+                return None;
+            }
+        };
+        Some(action)
+    }
+}
+
+#[cfg(any(debug_assertions, feature = "debug-tools"))]
+impl DebugAction {
+    fn from_name(name: &str) -> Option<DebugAction> {
+        let action = match name {
+            "toggle_debug" => DebugAction::ToggleTools,
+            "toggle_grid" => DebugAction::ToggleGrid,
+            "toggle_missing_assets" => DebugAction::ToggleMissingAssets,
+            "toggle_log" => DebugAction::ToggleLog,
+            "next_log_target" => DebugAction::NextLogTarget,
+            "log_more_verbose" => DebugAction::LogMoreVerbose,
+            "log_less_verbose" => DebugAction::LogLessVerbose,
+            "mute_master" => DebugAction::ToggleMute(Bus::Master),
+            "mute_music" => DebugAction::ToggleMute(Bus::Category(AudioCategory::Music)),
+            "mute_ambience" => DebugAction::ToggleMute(Bus::Category(AudioCategory::Ambience)),
+            "mute_dialog" => DebugAction::ToggleMute(Bus::Category(AudioCategory::Dialog)),
+            "mute_sound_effects" => {
+                DebugAction::ToggleMute(Bus::Category(AudioCategory::SoundEffect))
+            }
+            "volume_down" => DebugAction::VolumeDown,
+            "volume_up" => DebugAction::VolumeUp,
+            "toggle_instant_move" => DebugAction::ToggleInstantMove,
+            "toggle_bounding_boxes" => DebugAction::ToggleBoundingBoxes,
+            "animation_faster" => DebugAction::AnimationFaster,
+            "animation_slower" => DebugAction::AnimationSlower,
+            "toggle_level_switch" => DebugAction::ToggleLevelSwitch,
+            _ => {
+                let level = name.strip_prefix("switch_level_")?.parse().ok()?;
+                DebugAction::SwitchLevel(level)
+            }
+        };
+        Some(action)
+    }
+}
+
+// Keys for every action: the defaults, overridden by the bindings in the settings.
+// With the `gamepad` feature actions can also be triggered from a gamepad.
+pub struct Bindings {
+    keys: HashMap<Action, KeyCode>,
+    #[cfg(feature = "gamepad")]
+    buttons: HashMap<Action, Button>,
+    #[cfg(feature = "gamepad")]
+    gamepads: Gamepads,
+}
+
+impl Bindings {
+    pub fn new(settings: &Settings) -> Self {
+        let mut bindings = Bindings {
+            keys: HashMap::new(),
+            #[cfg(feature = "gamepad")]
+            buttons: HashMap::new(),
+            #[cfg(feature = "gamepad")]
+            gamepads: Gamepads::new(),
+        };
+        #[cfg(any(debug_assertions, feature = "debug-tools"))]
+        bindings.bind_all(settings::DEBUG_KEY_BINDINGS.iter().copied());
+        bindings.bind_all(settings::DEFAULT_KEY_BINDINGS.iter().copied());
+        bindings.bind_all(
+            settings
+                .key_bindings
+                .iter()
+                .map(|(action, key)| (action.as_str(), key.as_str())),
+        );
+        #[cfg(feature = "gamepad")]
+        for &(name, button_name) in settings::DEFAULT_GAMEPAD_BINDINGS {
+            if let (Some(action), Some(button)) =
+                (Action::from_name(name), button_from_name(button_name))
+            {
+                bindings.buttons.insert(action, button);
+            }
+        }
+        bindings
+    }
+
+    // Polls the gamepads, call once at the start of every frame
+    pub fn update(&mut self) {
+        #[cfg(feature = "gamepad")]
+        self.gamepads.update();
+    }
+
+    fn bind_all<'a>(&mut self, bindings: impl Iterator<Item = (&'a str, &'a str)>) {
+        for (name, key_name) in bindings {
+            let Some(action) = Action::from_name(name) else {
+                log::warn!(target: logging::INPUT, "Ignoring binding for unknown action {:?}", name);
+                continue;
+            };
+            match key_from_name(key_name) {
+                Some(key) => {
+                    self.keys.insert(action, key);
+                }
+                None => log::warn!(
+                    target: logging::INPUT,
+                    "Unknown key {:?} bound to {}, using the default",
+                    key_name,
+                    name
+                ),
+            }
+        }
+    }
+
+    pub fn is_pressed(&self, action: Action) -> bool {
+        #[cfg(feature = "gamepad")]
+        if let Some(&button) = self.buttons.get(&action)
+            && self.gamepads.is_pressed(button)
+        {
+            return true;
+        }
+        self.keys
+            .get(&action)
+            .is_some_and(|&key| is_key_pressed(key))
+    }
+
+    // Whether the action's key or button is held, for actions that last while held
+    pub fn is_down(&self, action: Action) -> bool {
+        #[cfg(feature = "gamepad")]
+        if let Some(&button) = self.buttons.get(&action)
+            && self.gamepads.is_down(button)
+        {
+            return true;
+        }
+        self.keys.get(&action).is_some_and(|&key| is_key_down(key))
+    }
+
+    // Direction to move the virtual cursor in, from the gamepad stick or, when
+    // `use_keys` is set, the cursor keys. The length is at most 1.
+    pub fn cursor_direction(&self, use_keys: bool) -> Vec2 {
+        #[cfg(feature = "gamepad")]
+        if self.gamepads.stick() != Vec2::ZERO {
+            return self.gamepads.stick();
+        }
+        if !use_keys {
+            return Vec2::ZERO;
+        }
+        let axis = |negative, positive| {
+            (self.is_down(positive) as i32 - self.is_down(negative) as i32) as f32
+        };
+        Vec2::new(
+            axis(Action::CursorLeft, Action::CursorRight),
+            axis(Action::CursorUp, Action::CursorDown),
+        )
+        .normalize_or_zero()
+    }
+
+    #[cfg(any(debug_assertions, feature = "debug-tools"))]
+    pub fn is_debug_pressed(&self, action: DebugAction) -> bool {
+        self.is_pressed(Action::Debug(action))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bindings_with(key_bindings: &[(&str, &str)]) -> Bindings {
+        let settings = Settings {
+            key_bindings: key_bindings
+                .iter()
+                .map(|&(action, key)| (action.to_string(), key.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+        Bindings::new(&settings)
+    }
+
+    #[test]
+    fn action_names() {
+        assert_eq!(Action::from_name("skip_line"), Some(Action::SkipLine));
+        assert_eq!(
+            Action::from_name("select_character_2"),
+            Some(Action::SelectCharacter(1))
+        );
+        assert_eq!(Action::from_name("select_character_0"), None);
+        assert_eq!(Action::from_name("select_character_x"), None);
+        assert_eq!(Action::from_name("fly"), None);
+    }
+
+    #[test]
+    fn settings_override_default_keys() {
+        let bindings = bindings_with(&[("skip_line", "K")]);
+        assert_eq!(bindings.keys[&Action::SkipLine], KeyCode::K);
+        assert_eq!(bindings.keys[&Action::Pause], KeyCode::Escape);
+    }
+
+    #[test]
+    fn unknown_key_keeps_the_default() {
+        let bindings = bindings_with(&[("skip_line", "Hyper")]);
+        assert_eq!(bindings.keys[&Action::SkipLine], KeyCode::Period);
+    }
+
+    #[test]
+    fn unknown_action_is_ignored() {
+        let default = bindings_with(&[]);
+        let bindings = bindings_with(&[("fly", "K")]);
+        assert_eq!(bindings.keys, default.keys);
+    }
+}
@@ -0,0 +1,170 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Log targets, one per subsystem. Use them with the `log` macros, e.g.
+// `log::debug!(target: logging::AUDIO, "Playing audio: {}", name)`.
+pub const AUDIO: &str = "audio";
+pub const ASSETS: &str = "assets";
+pub const GRID: &str = "grid";
+pub const DIALOG: &str = "dialog";
+pub const INPUT: &str = "input";
+pub const GAME: &str = "game";
+
+pub const TARGETS: [&str; 6] = [AUDIO, ASSETS, GRID, DIALOG, INPUT, GAME];
+
+// Levels can be set at startup with e.g. `OPENJONSSON_LOG=info,audio=debug,grid=off`
+pub const LOG_ENV: &str = "OPENJONSSON_LOG";
+const HISTORY_SIZE: usize = 200;
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::Info;
+
+#[derive(Clone, Debug)]
+pub struct LogEntry {
+    pub level: Level,
+    pub target: &'static str,
+    pub message: String,
+}
+
+struct GameLogger {
+    // Indexed like TARGETS, values are `LevelFilter as usize`
+    levels: [AtomicUsize; TARGETS.len()],
+    history: Mutex<VecDeque<LogEntry>>,
+}
+
+static LOGGER: GameLogger = GameLogger {
+    levels: [const { AtomicUsize::new(DEFAULT_LEVEL as usize) }; TARGETS.len()],
+    history: Mutex::new(VecDeque::new()),
+};
+
+impl Log for GameLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let target = TARGETS
+            .iter()
+            .find(|&&t| t == record.target())
+            .copied()
+            .unwrap_or(GAME);
+        let message = record.args().to_string();
+        eprintln!("[{:<5} {}] {}", record.level(), target, message);
+
+        if let Ok(mut history) = self.history.lock() {
+            if history.len() == HISTORY_SIZE {
+                history.pop_front();
+            }
+            history.push_back(LogEntry {
+                level: record.level(),
+                target,
+                message,
+            });
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+pub fn init() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Trace);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Ok(spec) = std::env::var(LOG_ENV) {
+        apply_spec(&spec);
+    }
+}
+
+// Parses a comma separated list of `level` or `target=level` entries
+fn apply_spec(spec: &str) {
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (target, level) = match entry.split_once('=') {
+            Some((target, level)) => (Some(target.trim()), level.trim()),
+            None => (None, entry),
+        };
+        let Ok(level) = level.parse::<LevelFilter>() else {
+            log::warn!(target: GAME, "Ignoring invalid {} entry: {}", LOG_ENV, entry);
+            continue;
+        };
+        match target {
+            Some(target) if !TARGETS.contains(&target) => {
+                log::warn!(target: GAME, "Unknown log target in {}: {}", LOG_ENV, target);
+            }
+            Some(target) => set_level(target, level),
+            None => TARGETS.iter().for_each(|target| set_level(target, level)),
+        }
+    }
+}
+
+fn target_index(target: &str) -> usize {
+    TARGETS
+        .iter()
+        .position(|&t| t == target)
+        .unwrap_or(TARGETS.len() - 1)
+}
+
+pub fn level(target: &str) -> LevelFilter {
+    let value = LOGGER.levels[target_index(target)].load(Ordering::Relaxed);
+    LevelFilter::iter()
+        .find(|&l| l as usize == value)
+        .unwrap_or(DEFAULT_LEVEL)
+}
+
+pub fn set_level(target: &str, level: LevelFilter) {
+    LOGGER.levels[target_index(target)].store(level as usize, Ordering::Relaxed);
+}
+
+// Steps the level of a target up (more verbose) or down, stopping at Off and Trace
+#[cfg(any(debug_assertions, feature = "debug-tools"))]
+pub fn step_level(target: &str, more_verbose: bool) {
+    let current = level(target) as usize;
+    let next = if more_verbose {
+        (current + 1).min(LevelFilter::Trace as usize)
+    } else {
+        current.saturating_sub(1)
+    };
+    if let Some(next) = LevelFilter::iter().find(|&l| l as usize == next) {
+        set_level(target, next);
+    }
+}
+
+pub fn recent(count: usize) -> Vec<LogEntry> {
+    LOGGER
+        .history
+        .lock()
+        .map(|history| history.iter().rev().take(count).rev().cloned().collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The levels are global, so the cases run in order in a single test
+    #[test]
+    fn apply_spec_sets_levels() {
+        apply_spec("warn");
+        assert!(TARGETS.iter().all(|&t| level(t) == LevelFilter::Warn));
+
+        apply_spec("info, audio=debug ,grid=off");
+        assert_eq!(level(AUDIO), LevelFilter::Debug);
+        assert_eq!(level(GRID), LevelFilter::Off);
+        assert_eq!(level(GAME), LevelFilter::Info);
+
+        // Invalid levels, unknown targets and empty entries are skipped
+        apply_spec("audio=loud,physics=trace,,=debug");
+        assert_eq!(level(AUDIO), LevelFilter::Debug);
+        assert_eq!(level(GAME), LevelFilter::Info);
+
+        apply_spec("");
+        assert_eq!(level(GRID), LevelFilter::Off);
+
+        // Unknown targets are logged under the game target
+        assert_eq!(level("physics"), level(GAME));
+    }
+}
@@ -1,23 +1,57 @@
+mod asset_audit;
 mod asset_manager;
+mod asset_resolver;
 mod audio;
 mod config;
+mod cursor;
 mod dialog;
+mod error;
+#[cfg(feature = "gamepad")]
+mod gamepad;
 mod grid;
+mod input;
+mod logging;
+mod menu;
+mod music;
+mod npc;
 mod renderer;
+mod sequence;
+mod settings;
+mod strings;
+mod video;
+mod voice;
 
 use crate::config::{character, inventory};
 use crate::dialog::{Dialog, DialogMenu};
-use crate::grid::Grid;
+use crate::grid::{Grid, Surface, SurfaceMap};
+use asset_audit::AssetAudit;
 use asset_manager::AssetManager;
-use audio::{AudioCategory, AudioSystem};
+use asset_resolver::AssetResolver;
+use audio::{AudioCategory, AudioSystem, Bus, Emitter};
+use cursor::{Snap, VirtualCursor};
+use error::{AssetError, GameError, GridError};
+#[cfg(any(debug_assertions, feature = "debug-tools"))]
+use input::DebugAction;
+use input::{Action, Bindings};
 use macroquad::prelude::*;
 use macroquad::rand::ChooseRandom;
 use macroquad::time::get_fps;
+use menu::{Menu, MenuAction, MenuScreen};
+use music::MusicPlayer;
+use npc::{Consequence, Npc};
 use renderer::Renderer;
+use sequence::{SequencePlayer, Step};
 use serde::Deserialize;
-use std::collections::HashMap;
+use settings::Settings;
+use std::collections::{HashMap, HashSet};
+use strings::Strings;
+use video::{Video, VideoPlayer};
+use voice::{VoiceEvent, VoiceLine, VoiceQueue};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+const DEBUG_TEXTURE: &str = "berlin/Internal/13.png";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Direction {
     SouthWest = 1,
     West = 2,
@@ -29,6 +63,31 @@ pub enum Direction {
     South = 8,
 }
 
+impl Direction {
+    // Unit vector pointing the way the direction faces on screen
+    pub fn vector(self) -> Vec2 {
+        let (x, y) = match self {
+            Direction::SouthWest => (-1.0, 1.0),
+            Direction::West => (-1.0, 0.0),
+            Direction::NorthWest => (-1.0, -1.0),
+            Direction::North => (0.0, -1.0),
+            Direction::NorthEast => (1.0, -1.0),
+            Direction::East => (1.0, 0.0),
+            Direction::SouthEast => (1.0, 1.0),
+            Direction::South => (0.0, 1.0),
+        };
+        Vec2::new(x, y).normalize()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimationState {
+    Idle,
+    Walking,
+    // Loudness of the voice line being said, in 0..1
+    Talking(f32),
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
 pub enum CursorType {
     #[serde(rename = "normal")]
@@ -39,6 +98,43 @@ pub enum CursorType {
     Take,
     #[serde(rename = "talk")]
     Talk,
+    #[serde(rename = "look")]
+    Look,
+}
+
+// Each kind holds the index of what it refers to: a world item of the current
+// scene, one of its dialogs, transitions or look regions, a character or an NPC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotspotKind {
+    Item(usize),
+    Dialog(usize),
+    Transition(usize),
+    Look(usize),
+    Character(usize),
+    Npc(usize),
+}
+
+impl HotspotKind {
+    fn cursor(&self) -> CursorType {
+        match self {
+            HotspotKind::Item(_) => CursorType::Take,
+            HotspotKind::Dialog(_) => CursorType::Talk,
+            HotspotKind::Transition(_) => CursorType::Move,
+            HotspotKind::Look(_) => CursorType::Look,
+            HotspotKind::Character(_) => CursorType::Normal,
+            HotspotKind::Npc(_) => CursorType::Talk,
+        }
+    }
+}
+
+// Something in the current scene that can be clicked, in game coordinates
+#[derive(Debug, Clone)]
+pub struct Hotspot {
+    pub kind: HotspotKind,
+    pub rect: Rect,
+    pub label: String,
+    // Items out of the active character's reach can't be interacted with yet
+    pub in_range: bool,
 }
 
 #[derive(Deserialize, Debug, Clone, Copy)]
@@ -63,6 +159,32 @@ pub struct SceneTransition {
     pub height: f32,
     #[serde(rename = "targetScene")]
     pub target_scene: u32,
+    #[serde(default)]
+    pub descriptions: Descriptions,
+}
+
+// What a character says when examining something: a voice line picked at
+// random from `audio`, with `text` as its subtitle. Either can be left out.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Description {
+    #[serde(default)]
+    pub audio: Vec<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+// Descriptions by character name, "default" for everyone without their own
+pub type Descriptions = HashMap<String, Description>;
+
+// Scenery that can only be examined, e.g. a painting on the wall
+#[derive(Deserialize, Debug, Clone)]
+pub struct LookRegion {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub name: String,
+    pub descriptions: Descriptions,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -75,6 +197,11 @@ pub struct BlockedNodeData {
     level_id: u32,
     scene_id: u32,
     blocked_nodes: Vec<(i32, i32)>,
+    // Nodes tagged with a floor surface, e.g. `"wood": [[10, 12], [10, 13]]`
+    #[serde(default)]
+    surfaces: HashMap<Surface, Vec<(i32, i32)>>,
+    #[serde(default)]
+    default_surface: Option<Surface>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -89,8 +216,79 @@ pub struct Scene {
     pub items: Vec<ItemInstance>,
     #[serde(skip)]
     pub blocked_nodes: Vec<(i32, i32)>,
+    #[serde(skip)]
+    pub surfaces: SurfaceMap,
     pub dialogs: Vec<Dialog>,
     pub background_music: Option<String>,
+    // Tracks played in order, looping back to the first. Takes precedence over
+    // `background_music` when not empty.
+    #[serde(default)]
+    pub music_playlist: Vec<String>,
+    // Looping sounds played on top of the music, e.g. room tone
+    #[serde(default)]
+    pub ambient_layers: Vec<String>,
+    // Looping sounds placed in the scene, panned by their position
+    #[serde(default)]
+    pub ambient_sources: Vec<AmbientSource>,
+    // Scenes in the same area keep the current track playing between them
+    #[serde(default)]
+    pub music_area: Option<String>,
+    // Crossfade length in seconds, overriding config::audio::MUSIC_CROSSFADE
+    #[serde(default)]
+    pub music_crossfade: Option<f32>,
+    #[serde(default)]
+    pub look_regions: Vec<LookRegion>,
+    #[serde(default)]
+    pub sequences: Vec<sequence::Sequence>,
+    #[serde(default)]
+    pub npcs: Vec<npc::NpcData>,
+}
+
+impl Scene {
+    // Voice lines are stored per scene, e.g. `voice/GHV/GHV_DG1_D_1_1.wav`
+    pub fn voice_path(&self, audio_id: &str) -> String {
+        format!("voice/{}/{}_{}.wav", self.name, self.name, audio_id)
+    }
+
+    // The scene's music as a playlist, falling back to the single looping track
+    pub fn music_tracks(&self) -> Vec<String> {
+        if self.music_playlist.is_empty() {
+            self.background_music.iter().cloned().collect()
+        } else {
+            self.music_playlist.clone()
+        }
+    }
+
+    // Examine descriptions of the scene's dialogs, transitions and look regions
+    pub fn descriptions(&self) -> impl Iterator<Item = &Descriptions> {
+        let dialogs = self.dialogs.iter().map(|dialog| &dialog.descriptions);
+        let transitions = self.scene_transitions.iter().map(|t| &t.descriptions);
+        let look_regions = self.look_regions.iter().map(|region| &region.descriptions);
+        let npcs = self.npcs.iter().map(|npc| &npc.descriptions);
+        dialogs.chain(transitions).chain(look_regions).chain(npcs)
+    }
+}
+
+// Positions are in game space (1920x1440)
+#[derive(Debug, Clone, Deserialize)]
+pub struct AmbientSource {
+    pub audio: String,
+    pub x: f32,
+    pub y: f32,
+    // Get quieter the further the active character is from the source
+    #[serde(default)]
+    pub attenuate: bool,
+}
+
+impl AmbientSource {
+    pub fn emitter(&self) -> Emitter {
+        let position = Vec2::new(self.x, self.y);
+        if self.attenuate {
+            Emitter::attenuated(position)
+        } else {
+            Emitter::at(position)
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -99,6 +297,30 @@ pub struct CharacterData {
     pub speed: f32,
     pub run_speed: f32,
     pub select_audio: Vec<String>,
+    #[serde(default)]
+    pub talk_frames: Option<TalkFrames>,
+}
+
+// Mouth frames use the same naming as the walk frames under their own state
+// number, ordered from closed to wide open.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TalkFrames {
+    pub state: usize,
+    pub count: usize,
+}
+
+impl CharacterData {
+    pub fn frame_path(&self, direction: u8, frame: usize, state: usize) -> String {
+        sprite_frame_path(&self.name, direction, frame, state)
+    }
+}
+
+// Frames are named <name><direction><frame><state>.png, e.g. `sickan1007.png`
+pub fn sprite_frame_path(name: &str, direction: u8, frame: usize, state: usize) -> String {
+    format!(
+        "Huvudmeny/Gubbar/{}{}{}{}.png",
+        name, direction, frame, state
+    )
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -133,6 +355,35 @@ pub struct Item {
     pub textures: ItemTextures,
     pub allowed_characters: Vec<String>,
     pub pickup_audio: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub descriptions: Descriptions,
+}
+
+// Two inventory items that combine into a new one, used on each other in
+// either order
+#[derive(Deserialize, Debug, Clone)]
+pub struct Recipe {
+    pub items: [u32; 2],
+    pub result: u32,
+    // Characters who can combine them, anyone when empty
+    #[serde(default)]
+    pub allowed_characters: Vec<String>,
+    // Said by the character combining the items, keyed by character name
+    #[serde(default)]
+    pub audio: HashMap<String, Vec<String>>,
+    // Sound effect, the inventory sound when missing
+    #[serde(default)]
+    pub sound: Option<String>,
+}
+
+impl Recipe {
+    fn combines(&self, a: u32, b: u32) -> bool {
+        self.items == [a, b] || self.items == [b, a]
+    }
+
+    fn sound(&self) -> &str {
+        self.sound.as_deref().unwrap_or(inventory::COMBINE_SOUND)
+    }
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -150,16 +401,45 @@ pub struct InventoryData {
     pub animation_frame: usize,
     pub animation_timer: f32,
     pub button_rect: Rect,
-    pub items: Vec<Option<u32>>,
+    // Item ids carried by each character, in slot order, always packed from the
+    // first slot
+    pub items: Vec<Vec<u32>>,
+    // The character whose items are shown
+    pub owner: usize,
     pub scroll_offset: usize,
     pub hovered_slot: Option<usize>,
     pub left_arrow_rect: Rect,
     pub right_arrow_rect: Rect,
     pub hovered_left_arrow: bool,
     pub hovered_right_arrow: bool,
+    pub drag: Option<InventoryDrag>,
+    // Shown item just made by combining two others, highlighted until the
+    // timer runs out
+    pub combined: Option<usize>,
+    pub combine_timer: f32,
+}
+
+// An item being moved out of its slot
+#[derive(Clone, Copy, Debug)]
+pub struct InventoryDrag {
+    // Index into the shown items
+    pub index: usize,
+    start: Vec2,
+    // Picked up with a click rather than dragged, dropped by the next click
+    held: bool,
+}
+
+impl Default for InventoryData {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl InventoryData {
+    pub fn button_texture_path(frame: usize) -> String {
+        format!("Huvudmeny/inventory/väska{}.png", frame + 1)
+    }
+
     pub fn new() -> Self {
         let inventory_width = inventory::SLOT_SIZE * inventory::SLOT_COUNT as f32
             + inventory::SLOT_SPACING * (inventory::SLOT_COUNT - 1) as f32;
@@ -172,7 +452,8 @@ impl InventoryData {
             animation_frame: 0,
             animation_timer: 0.0,
             button_rect: Rect::new(1800.0, 1340.0, 100.0, 100.0),
-            items: vec![None; inventory::INVENTORY_SIZE],
+            items: Vec::new(),
+            owner: 0,
             scroll_offset: 0,
             hovered_slot: None,
             left_arrow_rect: Rect::new(
@@ -189,7 +470,99 @@ impl InventoryData {
             ),
             hovered_left_arrow: false,
             hovered_right_arrow: false,
+            drag: None,
+            combined: None,
+            combine_timer: 0.0,
+        }
+    }
+
+    // Items carried by the character whose inventory is shown
+    pub fn shown(&self) -> &[u32] {
+        self.items.get(self.owner).map_or(&[], Vec::as_slice)
+    }
+
+    fn carried_mut(&mut self, character: usize) -> &mut Vec<u32> {
+        if self.items.len() <= character {
+            self.items.resize_with(character + 1, Vec::new);
+        }
+        &mut self.items[character]
+    }
+
+    // Shows another character's items, from the first slot
+    pub fn show(&mut self, character: usize) {
+        self.owner = character;
+        self.scroll_offset = 0;
+        self.drag = None;
+        self.combined = None;
+    }
+
+    // Item in a visible slot, taking the scroll position into account
+    pub fn item_in_slot(&self, slot: usize) -> Option<u32> {
+        self.shown().get(self.scroll_offset + slot).copied()
+    }
+
+    pub fn is_full(&self, character: usize) -> bool {
+        self.items
+            .get(character)
+            .is_some_and(|items| items.len() >= inventory::INVENTORY_SIZE)
+    }
+
+    pub fn add(&mut self, character: usize, item_id: u32) -> bool {
+        if self.is_full(character) {
+            return false;
+        }
+        self.carried_mut(character).push(item_id);
+        true
+    }
+
+    // Takes an item out of the shown inventory
+    pub fn remove(&mut self, index: usize) -> Option<u32> {
+        let owner = self.owner;
+        let items = self.carried_mut(owner);
+        if index >= items.len() {
+            return None;
+        }
+        let item_id = items.remove(index);
+        self.combined = None;
+        self.scroll_by(0);
+        Some(item_id)
+    }
+
+    // Replaces two shown items with the item they make, in the slot of the
+    // second one
+    pub fn combine(&mut self, from: usize, onto: usize, result: u32) {
+        let owner = self.owner;
+        let items = self.carried_mut(owner);
+        if from == onto || from >= items.len() || onto >= items.len() {
+            return;
+        }
+        items[onto] = result;
+        items.remove(from);
+        let index = if from < onto { onto - 1 } else { onto };
+        self.combined = Some(index);
+        self.combine_timer = inventory::COMBINE_FLASH_TIME;
+        self.scroll_by(0);
+    }
+
+    // Moves an item to another position, shifting the items in between.
+    // Positions past the last item move it to the end.
+    pub fn move_item(&mut self, from: usize, to: usize) {
+        let owner = self.owner;
+        let items = self.carried_mut(owner);
+        if from >= items.len() {
+            return;
         }
+        let item_id = items.remove(from);
+        let to = to.min(items.len());
+        items.insert(to, item_id);
+        self.combined = None;
+    }
+
+    // Scrolls by a number of slots, never past the last item
+    pub fn scroll_by(&mut self, direction: i32) {
+        let max_scroll = self.shown().len().saturating_sub(inventory::SLOT_COUNT);
+        self.scroll_offset =
+            (self.scroll_offset as i32 + direction).clamp(0, max_scroll as i32) as usize;
     }
 }
 
@@ -206,6 +579,11 @@ pub struct MenuItem {
     pub texture: String,
     pub position: [f32; 2],
     pub size: [f32; 2],
+    // Items for a menu screen are only shown there, and become that screen's
+    // button when named after a menu action (e.g. `new_game`). Items without a
+    // screen are drawn over the game.
+    #[serde(default)]
+    pub screen: Option<MenuScreen>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -229,6 +607,16 @@ pub struct GameData {
     pub characters: Vec<CharacterData>,
     pub ui: UI,
     pub items: Vec<Item>,
+    #[serde(default)]
+    pub recipes: Vec<Recipe>,
+    #[serde(default)]
+    pub videos: Vec<Video>,
+    // Name of the video played before the title screen
+    #[serde(default)]
+    pub intro_video: Option<String>,
+    // Footstep clips per surface, one is picked at random for each step
+    #[serde(default)]
+    pub footsteps: HashMap<Surface, Vec<String>>,
     #[serde(skip_deserializing)]
     pub blocked_nodes: Vec<BlockedNodeData>,
 }
@@ -238,6 +626,7 @@ struct Characters {
     positions: Vec<Vec2>,
     directions: Vec<Direction>,
     animation_indices: Vec<usize>,
+    animation_states: Vec<AnimationState>,
     animation_timers: Vec<f32>,
     animation_speeds: Vec<f32>,
     paths: Vec<Option<Vec<(i32, i32)>>>,
@@ -249,7 +638,6 @@ struct Characters {
 
 struct Scenes {
     data: Vec<Scene>,
-    count: usize,
 }
 
 struct Game {
@@ -267,12 +655,41 @@ struct Game {
     debug_instant_move: bool,
     debug_level_switch_mode: bool,
     items: Vec<Item>,
+    footsteps: HashMap<Surface, Vec<String>>,
+    recipes: Vec<Recipe>,
+    videos: Vec<Video>,
+    video_player: VideoPlayer,
     world_items: Vec<Vec<ItemInstance>>,
+    // World items of the levels left during this game, with the items picked
+    // up and placed by the player, by level id
+    level_items: HashMap<u32, Vec<Vec<ItemInstance>>>,
     renderer: Renderer,
     asset_manager: AssetManager,
     inventory: InventoryData,
     dialog_menu: DialogMenu,
     audio_system: AudioSystem,
+    music_player: MusicPlayer,
+    voice_queue: VoiceQueue,
+    sequence_player: SequencePlayer,
+    // NPCs of the current scene that are there according to their schedule
+    npcs: Vec<Npc>,
+    // Game state set by sequences, which NPC schedules depend on
+    flags: HashSet<String>,
+    // Where the gang entered the current scene, where guards send them back to
+    scene_entrance: Vec2,
+    asset_audit: AssetAudit,
+    settings: Settings,
+    input: Bindings,
+    strings: Strings,
+    // Verb and name of the hotspot under the cursor, e.g. "Prata med Doris"
+    hover_label: Option<String>,
+    cursor: VirtualCursor,
+    // Markers are drawn over every hotspot while the reveal key is held
+    reveal_hotspots: bool,
+    // Set when a setting is changed in game, saved at the end of the frame
+    settings_changed: bool,
+    menu: Menu,
+    quit_requested: bool,
 }
 
 struct DebugTools {
@@ -281,6 +698,9 @@ struct DebugTools {
     current_bounding_box: Option<Rect>,
     active: bool,
     draw_grid: bool,
+    show_missing_assets: bool,
+    show_log: bool,
+    log_target: usize,
 }
 
 impl DebugTools {
@@ -291,6 +711,9 @@ impl DebugTools {
             current_bounding_box: None,
             active: false,
             draw_grid: false,
+            show_missing_assets: false,
+            show_log: false,
+            log_target: 0,
         }
     }
 
@@ -307,7 +730,8 @@ impl DebugTools {
             self.current_bounding_box = Some(rect);
             self.bounding_box_start = None;
 
-            println!(
+            log::info!(
+                target: logging::INPUT,
                 "Bounding Box: x: {}, y: {}, width: {}, height: {}",
                 x,
                 y,
@@ -322,34 +746,54 @@ impl DebugTools {
 }
 
 impl Game {
-    async fn new() -> Result<Self, String> {
-        let json = load_string("static/level_data.json").await.unwrap();
-        let mut game_data: GameData =
-            serde_json::from_str(&json).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+    async fn new(resolver: AssetResolver) -> Result<Self, GameError> {
+        let asset_manager = AssetManager::new(resolver);
 
-        let blocked_nodes_json = load_string("static/blocked_nodes.json").await.unwrap();
-        let blocked_nodes: BlockedNodeDataCollection = serde_json::from_str(&blocked_nodes_json)
-            .map_err(|e| format!("Failed to parse blocked nodes JSON: {}", e))?;
+        let mut game_data: GameData = asset_manager.load_data_json("level_data.json").await?;
+        let blocked_nodes: BlockedNodeDataCollection =
+            asset_manager.load_data_json("blocked_nodes.json").await?;
 
         game_data.blocked_nodes = blocked_nodes.blocked_node_data;
 
         for level in &mut game_data.levels {
             for scene in &mut level.scenes {
-                let blocked_node_data = game_data
+                if let Some(node_data) = game_data
                     .blocked_nodes
                     .iter()
                     .find(|b| b.level_id == level.id && b.scene_id == scene.id)
-                    .map(|b| b.blocked_nodes.clone())
-                    .unwrap_or_default();
-                scene.blocked_nodes = blocked_node_data;
+                {
+                    scene.blocked_nodes = node_data.blocked_nodes.clone();
+                    scene.surfaces =
+                        SurfaceMap::new(&node_data.surfaces, node_data.default_surface);
+                }
             }
         }
 
+        let settings = Settings::load();
+        let strings = Strings::load(&asset_manager, &settings.language).await;
+        let asset_audit = AssetAudit::run(&game_data, asset_manager.resolver());
+        log::info!(
+            target: logging::ASSETS,
+            "Asset audit: {} of {} referenced assets missing",
+            asset_audit.missing_count(),
+            asset_audit.checked_count()
+        );
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Err(e) = asset_audit.write_report(config::paths::MISSING_ASSETS_REPORT) {
+            log::error!(
+                target: logging::ASSETS,
+                "Failed to write {}: {}",
+                config::paths::MISSING_ASSETS_REPORT,
+                e
+            );
+        }
+
         let mut characters = Characters {
             data: Vec::new(),
             positions: Vec::new(),
             directions: Vec::new(),
             animation_indices: Vec::new(),
+            animation_states: Vec::new(),
             animation_timers: Vec::new(),
             animation_speeds: Vec::new(),
             paths: Vec::new(),
@@ -366,21 +810,38 @@ impl Game {
                 .push(Vec2::new(1000.0 + i as f32 * 100.0, 800.0));
             characters.directions.push(Direction::South);
             characters.animation_indices.push(0);
+            characters.animation_states.push(AnimationState::Idle);
             characters.animation_timers.push(0.0);
-            characters.animation_speeds.push(0.1);
+            characters.animation_speeds.push(settings.animation_speed);
             characters.paths.push(None);
             characters.targets.push(None);
             characters.count += 1;
         }
 
-        let scenes = Scenes {
-            data: Vec::new(),
-            count: 0,
-        };
+        let scenes = Scenes { data: Vec::new() };
+
+        // A recipe with an unknown item would put an item without textures
+        // into the inventory
+        let mut recipes = game_data.recipes;
+        recipes.retain(|recipe| {
+            let unknown = recipe
+                .items
+                .iter()
+                .chain([&recipe.result])
+                .find(|&&id| !game_data.items.iter().any(|item| item.id == id));
+            if let Some(id) = unknown {
+                log::warn!(
+                    target: logging::ASSETS,
+                    "Skipping the recipe for {:?}, item {} doesn't exist",
+                    recipe.items,
+                    id
+                );
+            }
+            unknown.is_none()
+        });
 
         let window_size = Vec2::new(screen_width(), screen_height());
         let renderer = Renderer::new(window_size);
-        let asset_manager = AssetManager::new();
 
         let mut game = Game {
             characters,
@@ -397,14 +858,39 @@ impl Game {
             debug_instant_move: false,
             debug_level_switch_mode: false,
             items: game_data.items,
+            footsteps: game_data.footsteps,
+            recipes,
+            videos: game_data.videos,
+            video_player: VideoPlayer::new(),
             world_items: Vec::new(),
+            level_items: HashMap::new(),
             renderer,
             asset_manager,
             inventory: InventoryData::new(),
             dialog_menu: DialogMenu::new(),
             audio_system: AudioSystem::new(),
+            music_player: MusicPlayer::new(),
+            voice_queue: VoiceQueue::new(settings.text_speed),
+            sequence_player: SequencePlayer::new(),
+            npcs: Vec::new(),
+            flags: HashSet::new(),
+            scene_entrance: character::DEFAULT_SPAWN,
+            asset_audit,
+            input: Bindings::new(&settings),
+            strings,
+            hover_label: None,
+            cursor: VirtualCursor::new(Vec2::ZERO),
+            reveal_hotspots: false,
+            settings,
+            settings_changed: false,
+            menu: Menu::new(),
+            quit_requested: false,
         };
 
+        game.settings.apply_audio(&mut game.audio_system);
+        game.renderer
+            .apply_display(game.settings.fullscreen, game.settings.resolution);
+
         game.load_level_scenes(game.current_level);
         game.load_audio_assets().await?;
         game.load_current_and_adjacent_scenes().await;
@@ -415,6 +901,10 @@ impl Game {
         game.load_inventory_textures().await;
         game.load_item_textures().await;
 
+        if let Some(intro) = &game_data.intro_video {
+            game.play_video(intro).await;
+        }
+
         Ok(game)
     }
 
@@ -425,6 +915,12 @@ impl Game {
             for overlay_asset in &current_scene.overlay_assets {
                 textures_to_load.push(overlay_asset.texture_path.clone());
             }
+            for sequence in &current_scene.sequences {
+                textures_to_load.extend(sequence.textures().cloned());
+            }
+            for npc in &current_scene.npcs {
+                textures_to_load.extend(npc.texture_paths());
+            }
             for transition in &current_scene.scene_transitions {
                 if let Some(target_scene) = self.get_scene(transition.target_scene) {
                     textures_to_load.push(target_scene.background.clone());
@@ -434,26 +930,27 @@ impl Game {
         self.asset_manager.load_textures(&textures_to_load).await;
     }
 
-    async fn load_audio_assets(&mut self) -> Result<(), String> {
+    async fn load_audio_assets(&mut self) -> Result<(), AssetError> {
         let mut audio_files = std::collections::HashSet::new();
+        let mut positional_files = std::collections::HashSet::new();
         for level in &self.levels {
             for scene in &level.scenes {
-                if let Some(music) = &scene.background_music {
-                    audio_files.insert(music.clone());
+                audio_files.extend(scene.music_tracks());
+                audio_files.extend(scene.ambient_layers.iter().cloned());
+                for sequence in &scene.sequences {
+                    audio_files.extend(sequence.audio().cloned());
+                    positional_files.extend(sequence.voice_lines().cloned());
                 }
-                // Add dialog audio files if needed
+                positional_files.extend(scene.ambient_sources.iter().map(|s| s.audio.clone()));
+                // Dialog lines are said by the dialog's character, where they stand
                 for dialog in &scene.dialogs {
                     if let Some(open_audio) = &dialog.open_audio {
-                        let audio_path =
-                            format!("voice/{}/{}_{}.wav", scene.name, scene.name, open_audio);
-                        audio_files.insert(audio_path);
+                        positional_files.insert(scene.voice_path(open_audio));
                     }
                     for level in &dialog.tree {
                         for option in &level.options {
                             for audio in &option.response_audio {
-                                let audio_path =
-                                    format!("voice/{}/{}_{}.wav", scene.name, scene.name, audio);
-                                audio_files.insert(audio_path);
+                                positional_files.insert(scene.voice_path(audio));
                             }
                         }
                     }
@@ -465,60 +962,65 @@ impl Game {
         for item in &self.items {
             for audio_list in item.pickup_audio.values() {
                 for audio_file in audio_list {
-                    audio_files.insert(audio_file.clone());
+                    positional_files.insert(audio_file.clone());
                 }
             }
         }
 
+        // Examine lines, said by the character at their position
+        let scene_descriptions = self
+            .levels
+            .iter()
+            .flat_map(|level| &level.scenes)
+            .flat_map(|scene| scene.descriptions());
+        let item_descriptions = self.items.iter().map(|item| &item.descriptions);
+        for descriptions in scene_descriptions.chain(item_descriptions) {
+            for description in descriptions.values() {
+                positional_files.extend(description.audio.iter().cloned());
+            }
+        }
+
+        // Combining items
+        for recipe in &self.recipes {
+            positional_files.extend(recipe.audio.values().flatten().cloned());
+            if let Some(sound) = &recipe.sound {
+                audio_files.insert(sound.clone());
+            }
+        }
+
         // Add character audio files
         for c in &self.characters.data {
             for audio_file in &c.select_audio {
-                audio_files.insert(audio_file.clone());
+                positional_files.insert(audio_file.clone());
             }
         }
+        positional_files.extend(self.footsteps.values().flatten().cloned());
 
-        for audio_file in audio_files {
-            self.asset_manager.load_sound(&audio_file).await?;
-        }
+        self.asset_manager
+            .load_sounds(audio_files.difference(&positional_files))
+            .await;
+        self.asset_manager
+            .load_positional_sounds(&positional_files)
+            .await;
 
         // UI sounds
+        self.asset_manager.load_sound(inventory::OPEN_SOUND).await?;
         self.asset_manager
-            .load_sound("Huvudmeny/ljudfx/oppna.wav")
-            .await?;
-        self.asset_manager
-            .load_sound("Huvudmeny/ljudfx/stanga.wav")
+            .load_sound(inventory::CLOSE_SOUND)
             .await?;
         Ok(())
     }
 
-    // Update this method to work with the new AudioSystem
     fn update_scene_audio(&mut self) {
-        let music_to_play = self
-            .get_current_scene()
-            .and_then(|scene| scene.background_music.clone());
-
-        match music_to_play {
-            Some(music) => {
-                // Check if the music is already playing
-                if self
-                    .audio_system
-                    .currently_playing
-                    .get(&AudioCategory::Music)
-                    != Some(&Some(music.clone()))
-                {
-                    self.audio_system.play_music(&self.asset_manager, &music);
-                }
-            }
-            None => {
-                // Stop the music if there's no background music for this scene
-                self.audio_system.stop_music(&self.asset_manager);
-            }
+        if let Some(scene) = self.scenes.data.get(self.current_scene as usize) {
+            self.music_player
+                .enter_scene(scene, &mut self.audio_system, &self.asset_manager);
         }
     }
 
-    async fn load_fonts(&mut self) -> Result<(), String> {
+    async fn load_fonts(&mut self) -> Result<(), AssetError> {
         self.asset_manager
-            .load_font("dialog", "static/fonts/LiberationSans-Regular.ttf")
+            .load_font("dialog", "fonts/LiberationSans-Regular.ttf")
             .await?;
         Ok(())
     }
@@ -529,11 +1031,17 @@ impl Game {
             for dir in 1..=8 {
                 for frame in 0..=7 {
                     for state in [0, 7] {
-                        let filename =
-                            format!("{}{}{}{}.png", character_data.name, dir, frame, state);
-                        let path = format!("Huvudmeny/Gubbar/{}", filename);
+                        let path = character_data.frame_path(dir, frame, state);
+                        if let Err(e) = self.asset_manager.load_texture(&path).await {
+                            log::warn!(target: logging::ASSETS, "{}", e);
+                        }
+                    }
+                }
+                if let Some(talk) = &character_data.talk_frames {
+                    for frame in 0..talk.count {
+                        let path = character_data.frame_path(dir, frame, talk.state);
                         if let Err(e) = self.asset_manager.load_texture(&path).await {
-                            eprintln!("{}", e);
+                            log::warn!(target: logging::ASSETS, "{}", e);
                         }
                     }
                 }
@@ -542,25 +1050,21 @@ impl Game {
     }
 
     async fn load_debug_textures(&mut self) {
-        if let Err(e) = self
-            .asset_manager
-            .load_texture("berlin/Internal/13.png")
-            .await
-        {
-            eprintln!("{}", e);
+        if let Err(e) = self.asset_manager.load_texture(DEBUG_TEXTURE).await {
+            log::warn!(target: logging::ASSETS, "{}", e);
         }
     }
 
     async fn load_ui_textures(&mut self) {
         for cursor in &self.ui.cursors {
             if let Err(e) = self.asset_manager.load_texture(&cursor.texture).await {
-                eprintln!("{}", e);
+                log::warn!(target: logging::ASSETS, "{}", e);
             }
         }
 
         for menu_item in &self.ui.menu_items {
             if let Err(e) = self.asset_manager.load_texture(&menu_item.texture).await {
-                eprintln!("{}", e);
+                log::warn!(target: logging::ASSETS, "{}", e);
             }
         }
 
@@ -569,7 +1073,7 @@ impl Game {
             .load_texture(&self.ui.general_textures.dialog_background)
             .await
         {
-            eprintln!("{}", e);
+            log::warn!(target: logging::ASSETS, "{}", e);
         }
     }
 
@@ -587,21 +1091,21 @@ impl Game {
     }
 
     async fn load_inventory_textures(&mut self) {
-        for i in 1..=13 {
-            let path = format!("Huvudmeny/inventory/väska{}.png", i);
+        for frame in 0..inventory::BUTTON_FRAMES {
+            let path = InventoryData::button_texture_path(frame);
             if let Err(e) = self.asset_manager.load_texture(&path).await {
-                eprintln!("{}", e);
+                log::warn!(target: logging::ASSETS, "{}", e);
             }
         }
 
         // Load arrow textures
         let arrow_paths = [
-            "Huvudmeny/inventory/pilv-271.png",
-            "Huvudmeny/inventory/pilh-272.png",
+            inventory::LEFT_ARROW_TEXTURE,
+            inventory::RIGHT_ARROW_TEXTURE,
         ];
         for path in arrow_paths.iter() {
             if let Err(e) = self.asset_manager.load_texture(path).await {
-                eprintln!("{}", e);
+                log::warn!(target: logging::ASSETS, "{}", e);
             }
         }
     }
@@ -610,7 +1114,6 @@ impl Game {
         if let Some(level) = self.levels.iter().find(|l| l.id == level_id) {
             self.scenes = Scenes {
                 data: level.scenes.clone(),
-                count: level.scenes.len(),
             };
             //self.world_items = level.scenes.iter().map(|s| s.items.clone()).collect();
             self.world_items = level
@@ -630,16 +1133,29 @@ impl Game {
                         .collect::<Vec<ItemInstance>>()
                 })
                 .collect();
+            if let Some(items) = self.level_items.get(&level_id) {
+                self.world_items = items.clone();
+            }
             //self.current_scene = 0; // Reset to the first scene of the new level
 
             // Update blocked nodes in the grid
             if let Some(current_scene) = self.get_current_scene() {
-                self.grid
-                    .update_blocked_nodes(current_scene.blocked_nodes.clone());
+                let blocked_nodes = current_scene.blocked_nodes.clone();
+                let surfaces = current_scene.surfaces.clone();
+                self.grid.update_blocked_nodes(blocked_nodes);
+                self.grid.update_surfaces(surfaces);
             }
         }
     }
 
+    // Keeps the world items of the current level for when it is loaded again
+    fn store_level_items(&mut self) {
+        if !self.world_items.is_empty() {
+            let items = std::mem::take(&mut self.world_items);
+            self.level_items.insert(self.current_level, items);
+        }
+    }
+
     fn get_current_scene(&self) -> Option<&Scene> {
         self.scenes.data.get(self.current_scene as usize)
     }
@@ -653,41 +1169,129 @@ impl Game {
     }
 
     fn determine_cursor(&self, game_pos: Vec2) -> CursorType {
-        // Check for items first
-        let current_scene_items = &self.world_items[self.current_scene as usize];
-        for item in current_scene_items {
-            if self.is_mouse_over_item(game_pos, item) && self.is_item_in_range(item) {
-                return CursorType::Take;
-            }
+        self.hotspots()
+            .iter()
+            .find(|hotspot| {
+                hotspot.in_range
+                    && !matches!(hotspot.kind, HotspotKind::Character(_))
+                    && hotspot.rect.contains(game_pos)
+            })
+            .map_or(CursorType::Normal, |hotspot| hotspot.kind.cursor())
+    }
+
+    // What clicking at the position would do, e.g. "Prata med Doris"
+    fn describe_hotspot_at(&self, game_pos: Vec2) -> Option<String> {
+        let hotspot = self
+            .hotspots()
+            .into_iter()
+            .find(|hotspot| hotspot.rect.contains(game_pos))?;
+        if hotspot.label.is_empty() {
+            return None;
         }
+        let template = match hotspot.kind {
+            HotspotKind::Item(_) if hotspot.in_range => "hover.take",
+            HotspotKind::Item(_) => "hover.walk_to",
+            HotspotKind::Dialog(_) => "hover.talk_to",
+            HotspotKind::Transition(_) => "hover.go_to",
+            HotspotKind::Look(_) => "hover.look_at",
+            HotspotKind::Character(index) if Some(index) == self.active_character => {
+                "hover.character"
+            }
+            HotspotKind::Character(_) => "hover.select",
+            HotspotKind::Npc(index) if self.npcs[index].data.dialog.is_some() => "hover.talk_to",
+            HotspotKind::Npc(_) => "hover.character",
+        };
+        Some(self.strings.format(template, &hotspot.label))
+    }
+
+    // Everything clickable in the current scene: items first, then dialog regions,
+    // scene transitions, characters and look regions, in the order they take precedence
+    fn hotspots(&self) -> Vec<Hotspot> {
+        let mut hotspots: Vec<Hotspot> = self.world_items[self.current_scene as usize]
+            .iter()
+            .enumerate()
+            .map(|(index, instance)| Hotspot {
+                kind: HotspotKind::Item(index),
+                rect: Rect::new(instance.x, instance.y, instance.width, instance.height),
+                label: self
+                    .items
+                    .iter()
+                    .find(|item| item.id == instance.item_id)
+                    .map(|item| self.strings.get(&item.name).to_string())
+                    .unwrap_or_default(),
+                in_range: self.is_item_in_range(instance),
+            })
+            .collect();
 
-        // Then check for clickable areas
         if let Some(current_scene) = self.get_current_scene() {
-            // Check for dialog regions
-            for dialog in &current_scene.dialogs {
-                if game_pos.x >= dialog.x
-                    && game_pos.x <= dialog.x + dialog.width
-                    && game_pos.y >= dialog.y
-                    && game_pos.y <= dialog.y + dialog.height
-                {
-                    return CursorType::Talk;
-                }
+            hotspots.extend(
+                current_scene
+                    .dialogs
+                    .iter()
+                    .enumerate()
+                    .map(|(index, dialog)| Hotspot {
+                        kind: HotspotKind::Dialog(index),
+                        rect: Rect::new(dialog.x, dialog.y, dialog.width, dialog.height),
+                        label: self.strings.get(&dialog.description).to_string(),
+                        in_range: true,
+                    }),
+            );
+            hotspots.extend(current_scene.scene_transitions.iter().enumerate().map(
+                |(index, transition)| {
+                    Hotspot {
+                        kind: HotspotKind::Transition(index),
+                        rect: Rect::new(
+                            transition.x,
+                            transition.y,
+                            transition.width,
+                            transition.height,
+                        ),
+                        label: self
+                            .get_scene(transition.target_scene)
+                            .map(|scene| self.strings.get(&scene.name).to_string())
+                            .unwrap_or_default(),
+                        in_range: true,
+                    }
+                },
+            ));
+        }
+
+        hotspots.extend((0..self.characters.count).map(|index| {
+            let position = self.characters.positions[index];
+            Hotspot {
+                kind: HotspotKind::Character(index),
+                rect: Rect::new(
+                    position.x + character::X_OFFSET - character::WIDTH / 2.0,
+                    position.y + character::Y_OFFSET - character::HEIGHT / 2.0,
+                    character::WIDTH,
+                    character::HEIGHT,
+                ),
+                label: self
+                    .strings
+                    .get(&self.characters.data[index].name)
+                    .to_string(),
+                in_range: true,
             }
+        }));
 
-            // Check for scene transitions
-            for st in &current_scene.scene_transitions {
-                if game_pos.x >= st.x
-                    && game_pos.x <= st.x + st.width
-                    && game_pos.y >= st.y
-                    && game_pos.y <= st.y + st.height
-                {
-                    return CursorType::Move;
-                }
-            }
-        }
+        hotspots.extend(self.npcs.iter().enumerate().map(|(index, npc)| Hotspot {
+            kind: HotspotKind::Npc(index),
+            rect: npc.rect(),
+            label: self.strings.get(&npc.data.name).to_string(),
+            in_range: true,
+        }));
 
-        // Default to normal cursor
-        CursorType::Normal
+        if let Some(current_scene) = self.get_current_scene() {
+            hotspots.extend(current_scene.look_regions.iter().enumerate().map(
+                |(index, region)| Hotspot {
+                    kind: HotspotKind::Look(index),
+                    rect: Rect::new(region.x, region.y, region.width, region.height),
+                    label: self.strings.get(&region.name).to_string(),
+                    in_range: true,
+                },
+            ));
+        }
+        hotspots
     }
 
     fn vec_to_direction(vec: Vec2) -> Direction {
@@ -763,12 +1367,14 @@ impl Game {
 
     fn handle_item_click(&mut self, game_pos: Vec2) {
         let current_scene = self.current_scene as usize;
-        let active_character_name = self
-            .active_character
-            .and_then(|index| self.characters.data.get(index))
-            .map(|character| character.name.clone());
+        let active_character = self.active_character.and_then(|index| {
+            self.characters
+                .data
+                .get(index)
+                .map(|character| (index, character.name.clone()))
+        });
 
-        if let Some(active_character_name) = active_character_name {
+        if let Some((active_index, active_character_name)) = active_character {
             let pickup_info = self.world_items[current_scene]
                 .iter()
                 .enumerate()
@@ -789,48 +1395,59 @@ impl Game {
                 });
 
             if let Some((item_index, item_id, world_item, is_allowed, maybe_audio)) = pickup_info {
-                if !self.is_item_in_range(&world_item) {
+                if !self.is_item_in_range(world_item) {
                     return;
                 }
+                let item_center = Vec2::new(
+                    world_item.x + world_item.width / 2.0,
+                    world_item.y + world_item.height / 2.0,
+                );
 
                 if is_allowed {
-                    if self.add_item_to_inventory(item_id) {
-                        println!("Item added to inventory");
+                    if self.inventory.add(active_index, item_id) {
+                        log::info!(target: logging::GAME, "Item added to inventory");
                         self.world_items[current_scene].remove(item_index);
                     } else {
-                        println!("Inventory is full!");
+                        log::info!(target: logging::GAME, "Inventory is full!");
+                        self.say_text(active_index, "inventory.full");
+                        return;
                     }
                 } else {
-                    println!("{} cannot pick up this item!", active_character_name);
+                    log::info!(
+                        target: logging::GAME,
+                        "{} cannot pick up this item!",
+                        active_character_name
+                    );
                 }
 
-                // Play pickup audio
-                if let Some(audio_files) = maybe_audio {
-                    if let Some(audio_file) = audio_files.choose() {
-                        self.audio_system.play_audio(
-                            &self.asset_manager,
-                            audio_file,
-                            AudioCategory::SoundEffect,
-                        );
-                    }
+                // Play pickup audio, or say the item can't be carried when the
+                // character has nothing recorded for it
+                if let Some(audio_files) = maybe_audio
+                    && let Some(audio_file) = audio_files.choose()
+                {
+                    let line = VoiceLine::new(audio_file.as_str(), active_character_name)
+                        .with_emitter(Emitter::at(item_center));
+                    self.voice_queue.play(&mut self.audio_system, vec![line]);
+                } else if !is_allowed {
+                    self.say_text(active_index, "inventory.cannot_carry");
                 }
             }
         }
     }
 
-    fn is_item_in_inventory(&self, item_id: u32) -> bool {
-        self.inventory
-            .items
-            .iter()
-            .any(|&item| item == Some(item_id))
-    }
-
     fn update_inventory_animation(&mut self, delta_time: f32) {
         const ANIMATION_SPEED: f32 = 0.03;
         const TOTAL_FRAMES: usize = 13;
 
         self.inventory.animation_timer += delta_time;
 
+        if self.inventory.combined.is_some() {
+            self.inventory.combine_timer -= delta_time;
+            if self.inventory.combine_timer <= 0.0 {
+                self.inventory.combined = None;
+            }
+        }
+
         if self.inventory.animation_timer >= ANIMATION_SPEED {
             self.inventory.animation_timer -= ANIMATION_SPEED;
 
@@ -885,70 +1502,311 @@ impl Game {
         is_double
     }
 
-    fn scroll_inventory(&mut self, direction: i32) {
-        let items_count = self
-            .inventory
-            .items
-            .iter()
-            .filter(|&item| item.is_some())
-            .count();
-        let max_scroll = items_count.saturating_sub(inventory::SLOT_COUNT);
+    // Drops a dragged item when the button is released. Releasing without moving
+    // keeps the item on the cursor until the next click.
+    fn update_inventory_drag(&mut self, game_pos: Vec2, released: bool) {
+        let Some(drag) = &mut self.inventory.drag else {
+            return;
+        };
+        if !self.inventory.open {
+            self.inventory.drag = None;
+            return;
+        }
+        if !released || drag.held {
+            return;
+        }
+        if game_pos.distance(drag.start) < inventory::DRAG_THRESHOLD {
+            drag.held = true;
+        } else {
+            self.drop_inventory_item(game_pos);
+        }
+    }
 
-        let new_scroll_offset = (self.inventory.scroll_offset as i32 + direction)
-            .max(0)
-            .min(max_scroll as i32) as usize;
+    fn drop_inventory_item(&mut self, game_pos: Vec2) {
+        let Some(drag) = self.inventory.drag.take() else {
+            return;
+        };
+        let Some(&item_id) = self.inventory.shown().get(drag.index) else {
+            return;
+        };
 
-        // Only update if the scroll actually changed
-        if new_scroll_offset != self.inventory.scroll_offset {
-            self.inventory.scroll_offset = new_scroll_offset;
+        if game_pos.y >= inventory::TOP_Y {
+            // Dropped on an item it combines with, on a slot, or on the bar
+            // past the last item
+            if let Some(slot) = self.inventory.hovered_slot {
+                let onto = self.inventory.scroll_offset + slot;
+                if !self.combine_inventory_items(drag.index, onto) {
+                    self.inventory.move_item(drag.index, onto);
+                }
+            }
+            return;
         }
-    }
 
-    fn add_item_to_inventory(&mut self, item_id: u32) -> bool {
-        if let Some(empty_slot) = self.inventory.items.iter_mut().find(|slot| slot.is_none()) {
-            *empty_slot = Some(item_id);
-            true
-        } else {
-            false // Inventory is full
+        let target = self
+            .hotspots()
+            .into_iter()
+            .find(|hotspot| hotspot.rect.contains(game_pos));
+        match target {
+            Some(target) => self.use_inventory_item(drag.index, item_id, Some(target)),
+            None => self.place_inventory_item(drag.index, item_id, game_pos),
         }
     }
 
-    fn toggle_inventory(&mut self) {
-        self.inventory.open = !self.inventory.open;
+    // Combines two shown items if a recipe makes something of them. Returns
+    // whether there was a recipe, even when the owner isn't able to use it.
+    fn combine_inventory_items(&mut self, from: usize, onto: usize) -> bool {
+        let shown = self.inventory.shown();
+        let (Some(&a), Some(&b)) = (shown.get(from), shown.get(onto)) else {
+            return false;
+        };
+        if from == onto {
+            return false;
+        }
+        let Some(recipe) = self.recipes.iter().find(|recipe| recipe.combines(a, b)) else {
+            return false;
+        };
 
-        let audio_path = if self.inventory.open {
-            "Huvudmeny/ljudfx/oppna.wav"
-        } else {
-            "Huvudmeny/ljudfx/stanga.wav"
+        let owner = self.inventory.owner;
+        let owner_name = self.characters.data[owner].name.clone();
+        if !recipe.allowed_characters.is_empty() && !recipe.allowed_characters.contains(&owner_name)
+        {
+            log::info!(
+                target: logging::GAME,
+                "{} cannot combine items {} and {}",
+                owner_name,
+                a,
+                b
+            );
+            self.say_text(owner, "inventory.cannot_combine");
+            return true;
+        }
+
+        let result = recipe.result;
+        let sound = recipe.sound().to_string();
+        let audio = recipe.audio.get(&owner_name).cloned();
+        log::info!(
+            target: logging::GAME,
+            "{} combined items {} and {} into {}",
+            owner_name,
+            a,
+            b,
+            result
+        );
+        self.inventory.combine(from, onto, result);
+
+        if let Err(e) =
+            self.audio_system
+                .play_audio(&self.asset_manager, &sound, AudioCategory::SoundEffect)
+        {
+            log::warn!(target: logging::AUDIO, "{}", e);
+        }
+        if let Some(audio_files) = audio
+            && let Some(audio_file) = audio_files.choose()
+        {
+            let line = VoiceLine::new(audio_file.as_str(), owner_name)
+                .with_emitter(Emitter::at(self.characters.positions[owner]));
+            self.voice_queue.play(&mut self.audio_system, vec![line]);
+        }
+        true
+    }
+
+    // Puts an item from the shown inventory down on the floor of the current
+    // scene, next to its owner. The item stays there when the scene changes.
+    fn place_inventory_item(&mut self, index: usize, item_id: u32, position: Vec2) {
+        let owner = self.inventory.owner;
+        if !self
+            .grid
+            .is_node_walkable(self.grid.get_grid_from_coord(position))
+        {
+            self.say_text(owner, "inventory.cannot_place");
+            return;
+        }
+        if self.characters.positions[owner].distance(position) > character::INTERACTION_RANGE {
+            self.say_text(owner, "inventory.too_far");
+            return;
+        }
+        let Some(size) = self.item_size(item_id) else {
+            log::warn!(target: logging::GAME, "Item {} has no size to place it with", item_id);
+            return;
+        };
+        let Some(item_id) = self.inventory.remove(index) else {
+            return;
         };
 
-        self.audio_system
-            .play_audio(&self.asset_manager, audio_path, AudioCategory::SoundEffect);
+        log::info!(
+            target: logging::GAME,
+            "Item {} placed at {:?} in scene {}",
+            item_id,
+            position,
+            self.current_scene
+        );
+        // The drop point is where the item touches the floor
+        self.world_items[self.current_scene as usize].push(ItemInstance {
+            item_id,
+            x: position.x - size.x / 2.0,
+            y: position.y - size.y,
+            width: size.x,
+            height: size.y,
+        });
     }
 
-    async fn handle_mouse_click(&mut self, game_pos: Vec2) {
-        if !self.renderer.is_in_game_area(game_pos) {
+    // Size of an item lying in the world, as placed in the level data or else
+    // the size of its texture
+    fn item_size(&self, item_id: u32) -> Option<Vec2> {
+        let placed = self
+            .levels
+            .iter()
+            .flat_map(|level| &level.scenes)
+            .flat_map(|scene| &scene.items)
+            .find(|instance| instance.item_id == item_id);
+        if let Some(instance) = placed {
+            return Some(Vec2::new(instance.width, instance.height));
+        }
+        let item = self.items.iter().find(|item| item.id == item_id)?;
+        self.asset_manager
+            .get_texture(&item.textures.in_world)
+            .map(|texture| texture.size())
+    }
+
+    // Uses an inventory item on something in the scene. Items dropped on another
+    // character are handed over, nothing else reacts to items yet, so the owner
+    // says it doesn't work and the item stays.
+    fn use_inventory_item(&mut self, index: usize, item_id: u32, target: Option<Hotspot>) {
+        log::info!(
+            target: logging::GAME,
+            "Item {} (slot {}) used on {:?}",
+            item_id,
+            index,
+            target.as_ref().map(|hotspot| &hotspot.label)
+        );
+        let owner = self.inventory.owner;
+        match target.map(|hotspot| hotspot.kind) {
+            Some(HotspotKind::Character(receiver)) if receiver != owner => {
+                self.give_inventory_item(index, item_id, receiver);
+            }
+            _ => self.say_text(owner, "inventory.cannot_use"),
+        }
+    }
+
+    // Hands an item from the shown inventory to another character standing next
+    // to its owner, if they are allowed to carry it
+    fn give_inventory_item(&mut self, index: usize, item_id: u32, receiver: usize) {
+        let owner = self.inventory.owner;
+        let positions = &self.characters.positions;
+        if positions[owner].distance(positions[receiver]) > character::INTERACTION_RANGE {
+            self.say_text(owner, "inventory.too_far");
             return;
         }
 
-        if self.inventory.button_rect.contains(game_pos) {
-            self.toggle_inventory();
+        let receiver_name = self.characters.data[receiver].name.clone();
+        let Some(item) = self.items.iter().find(|item| item.id == item_id) else {
+            return;
+        };
+        if !item.allowed_characters.contains(&receiver_name) {
+            log::info!(
+                target: logging::GAME,
+                "{} cannot carry {}",
+                receiver_name,
+                item.name
+            );
+            self.say_text(receiver, "inventory.cannot_carry");
+            return;
+        }
+        if self.inventory.is_full(receiver) {
+            self.say_text(receiver, "inventory.full");
             return;
         }
 
-        // Handle inventory interaction
-        if self.inventory.open {
-            let inventory_top = inventory::START_Y - 59.0;
-            // Check if click is inside or below the inventory area
-            if game_pos.y >= inventory_top {
-                // Handle left arrow click
-                if self.inventory.left_arrow_rect.contains(game_pos) {
-                    self.scroll_inventory(-1);
-                    return;
+        let pickup_audio = item.pickup_audio.get(&receiver_name).cloned();
+        log::info!(
+            target: logging::GAME,
+            "{} gave {} to {}",
+            self.characters.data[owner].name,
+            item.name,
+            receiver_name
+        );
+        if let Some(item_id) = self.inventory.remove(index) {
+            self.inventory.add(receiver, item_id);
+        }
+
+        if let Some(audio_files) = pickup_audio
+            && let Some(audio_file) = audio_files.choose()
+        {
+            let line = VoiceLine::new(audio_file.as_str(), receiver_name)
+                .with_emitter(Emitter::at(self.characters.positions[receiver]));
+            self.voice_queue.play(&mut self.audio_system, vec![line]);
+        }
+    }
+
+    // A character says a line from the strings table, as a subtitle
+    fn say_text(&mut self, character: usize, key: &str) {
+        let Some(data) = self.characters.data.get(character) else {
+            return;
+        };
+        let mut line = VoiceLine::new("", data.name.as_str());
+        line.text = Some(self.strings.get(key).to_string());
+        self.voice_queue.play(&mut self.audio_system, vec![line]);
+    }
+
+    fn toggle_inventory(&mut self) {
+        self.inventory.open = !self.inventory.open;
+
+        let audio_path = if self.inventory.open {
+            inventory::OPEN_SOUND
+        } else {
+            inventory::CLOSE_SOUND
+        };
+
+        if let Err(e) = self.audio_system.play_audio(
+            &self.asset_manager,
+            audio_path,
+            AudioCategory::SoundEffect,
+        ) {
+            log::warn!(target: logging::AUDIO, "{}", e);
+        }
+    }
+
+    async fn handle_mouse_click(&mut self, game_pos: Vec2) {
+        if !self.renderer.is_in_game_area(game_pos) {
+            return;
+        }
+
+        // An item picked up with a click is put down by the next one
+        if let Some(drag) = self.inventory.drag
+            && drag.held
+        {
+            self.drop_inventory_item(game_pos);
+            return;
+        }
+
+        if self.inventory.button_rect.contains(game_pos) {
+            self.toggle_inventory();
+            return;
+        }
+
+        // Handle inventory interaction
+        if self.inventory.open {
+            let inventory_top = inventory::TOP_Y;
+            // Check if click is inside or below the inventory area
+            if game_pos.y >= inventory_top {
+                if let Some(slot) = self.inventory.hovered_slot
+                    && self.inventory.item_in_slot(slot).is_some()
+                {
+                    self.inventory.drag = Some(InventoryDrag {
+                        index: self.inventory.scroll_offset + slot,
+                        start: game_pos,
+                        held: false,
+                    });
+                    return;
+                }
+                // Handle left arrow click
+                if self.inventory.left_arrow_rect.contains(game_pos) {
+                    self.inventory.scroll_by(-1);
+                    return;
                 }
                 // Handle right arrow click
                 if self.inventory.right_arrow_rect.contains(game_pos) {
-                    self.scroll_inventory(1);
+                    self.inventory.scroll_by(1);
                     return;
                 }
                 // If we've reached here, the click was inside or below the inventory area
@@ -966,6 +1824,10 @@ impl Game {
         if self.dialog_menu.open {
             let in_dialog_area = game_pos.y >= config::dialog::START_Y && game_pos.y <= 1440.0;
             if in_dialog_area {
+                // Options can't be picked until the current answer has been said
+                if self.voice_queue.is_busy() {
+                    return;
+                }
                 if let Some(selected_option) = self.get_clicked_dialog_option(game_pos) {
                     self.handle_dialog_option_selection(selected_option);
                 }
@@ -980,20 +1842,21 @@ impl Game {
         // Check if a character was clicked
         if let Some(index) =
             (0..self.characters.count).find(|&i| self.is_point_in_character(game_pos, i))
+            && Some(index) != self.active_character
         {
-            if Some(index) != self.active_character {
-                self.active_character = Some(index);
+            self.select_character(index);
+            return;
+        }
 
-                // Play select character audio
-                if let Some(audio_file) = self.characters.data[index].select_audio.choose() {
-                    self.audio_system.play_audio(
-                        &self.asset_manager,
-                        audio_file,
-                        AudioCategory::SoundEffect,
-                    );
-                }
-                return;
-            }
+        // NPCs with a dialog open it when clicked
+        if let Some(dialog_id) = self
+            .npcs
+            .iter()
+            .find(|npc| npc.rect().contains(game_pos))
+            .and_then(|npc| npc.data.dialog)
+        {
+            self.open_dialog(dialog_id);
+            return;
         }
 
         // Check for dialog interactions
@@ -1015,13 +1878,13 @@ impl Game {
         }
 
         // Check for scene transitions and handle scene changes
-        if let Some(transition) = self.find_clicked_transition(game_pos) {
-            if self.debug_instant_move || self.is_active_character_in_transition_area(transition) {
-                let current_scene_id = self.current_scene;
-                self.current_scene = transition.target_scene;
-                self.transition_to_new_scene(current_scene_id).await;
-                return;
-            }
+        if let Some(transition) = self.find_clicked_transition(game_pos)
+            && (self.debug_instant_move || self.is_active_character_in_transition_area(transition))
+        {
+            let current_scene_id = self.current_scene;
+            self.current_scene = transition.target_scene;
+            self.transition_to_new_scene(current_scene_id).await;
+            return;
         }
 
         // Handle item clicks
@@ -1035,9 +1898,94 @@ impl Game {
         self.handle_pathfinding(game_pos).await;
     }
 
+    fn select_character(&mut self, index: usize) {
+        if Some(index) == self.active_character {
+            return;
+        }
+        self.active_character = Some(index);
+        self.inventory.show(index);
+
+        // Play select character audio
+        let character = &self.characters.data[index];
+        if let Some(audio_file) = character.select_audio.choose() {
+            let line = VoiceLine::new(audio_file.as_str(), character.name.as_str())
+                .with_emitter(Emitter::at(self.characters.positions[index]));
+            self.voice_queue.play(&mut self.audio_system, vec![line]);
+        }
+    }
+
     fn handle_right_click(&mut self, game_pos: Vec2) {
+        // Right click puts a dragged item back
+        if self.inventory.drag.take().is_some() {
+            return;
+        }
         if self.debug_tools.bounding_box_mode {
             self.debug_tools.handle_bounding_box_creation(game_pos);
+        } else if !self.dialog_menu.open {
+            self.examine(game_pos);
+        }
+    }
+
+    // The active character describes what is under the cursor
+    fn examine(&mut self, game_pos: Vec2) {
+        let Some(active_index) = self.active_character else {
+            return;
+        };
+        let character_name = &self.characters.data[active_index].name;
+        let Some(description) = self
+            .descriptions_at(game_pos)
+            .and_then(|d| d.get(character_name).or_else(|| d.get("default")))
+        else {
+            return;
+        };
+        if description.audio.is_empty() && description.text.is_none() {
+            return;
+        }
+
+        let mut line = VoiceLine::new(
+            description.audio.choose().cloned().unwrap_or_default(),
+            character_name.as_str(),
+        )
+        .with_emitter(Emitter::at(self.characters.positions[active_index]));
+        line.text = description
+            .text
+            .as_ref()
+            .map(|text| self.strings.get(text).to_string());
+        self.voice_queue.play(&mut self.audio_system, vec![line]);
+    }
+
+    // Examine descriptions of the inventory item or hotspot under the cursor
+    fn descriptions_at(&self, game_pos: Vec2) -> Option<&Descriptions> {
+        if self.inventory.open && game_pos.y >= inventory::TOP_Y {
+            let item_id = self
+                .inventory
+                .hovered_slot
+                .and_then(|slot| self.inventory.item_in_slot(slot))?;
+            return self
+                .items
+                .iter()
+                .find(|item| item.id == item_id)
+                .map(|item| &item.descriptions);
+        }
+
+        let hotspot = self
+            .hotspots()
+            .into_iter()
+            .find(|hotspot| hotspot.rect.contains(game_pos))?;
+        let scene = self.get_current_scene()?;
+        match hotspot.kind {
+            HotspotKind::Item(index) => {
+                let item_id = self.world_items[self.current_scene as usize][index].item_id;
+                self.items
+                    .iter()
+                    .find(|item| item.id == item_id)
+                    .map(|item| &item.descriptions)
+            }
+            HotspotKind::Dialog(index) => Some(&scene.dialogs[index].descriptions),
+            HotspotKind::Transition(index) => Some(&scene.scene_transitions[index].descriptions),
+            HotspotKind::Look(index) => Some(&scene.look_regions[index].descriptions),
+            HotspotKind::Character(_) => None,
+            HotspotKind::Npc(index) => Some(&self.npcs[index].data.descriptions),
         }
     }
 
@@ -1056,61 +2004,85 @@ impl Game {
         });
 
         if let Some(id) = dialog_id {
-            self.dialog_menu.open = true;
-            self.dialog_menu.current_dialog_id = Some(id);
-            self.play_open_dialog_sound(id);
+            self.open_dialog(id);
         }
     }
 
+    fn open_dialog(&mut self, dialog_id: u32) {
+        self.dialog_menu.open = true;
+        self.dialog_menu.current_dialog_id = Some(dialog_id);
+        self.play_open_dialog_sound(dialog_id);
+    }
+
     fn play_open_dialog_sound(&mut self, dialog_id: u32) {
-        if let Some(current_scene) = self.get_current_scene() {
-            if let Some(dialog) = current_scene.dialogs.iter().find(|d| d.id == dialog_id) {
-                if let Some(audio) = &dialog.open_audio {
-                    let audio_to_play = format!(
-                        "voice/{}/{}_{}.wav",
-                        current_scene.name, current_scene.name, audio
-                    );
-                    self.audio_system.play_audio(
-                        &self.asset_manager,
-                        &audio_to_play,
-                        AudioCategory::Dialog,
-                    );
-                }
-            }
+        if let Some(current_scene) = self.get_current_scene()
+            && let Some(dialog) = current_scene.dialogs.iter().find(|d| d.id == dialog_id)
+            && let Some(audio) = &dialog.open_audio
+        {
+            let mut line =
+                VoiceLine::new(current_scene.voice_path(audio), dialog.description.as_str());
+            line.emitter = self.speaker_emitter(&dialog.description, Some(dialog));
+            self.voice_queue.play(&mut self.audio_system, vec![line]);
         }
     }
 
+    // Dialog lines are played where the speaking character or NPC stands, or
+    // from the middle of the dialog's hotspot when the speaker isn't in sight
+    fn speaker_emitter(&self, speaker: &str, dialog: Option<&Dialog>) -> Option<Emitter> {
+        let character = self
+            .characters
+            .data
+            .iter()
+            .position(|character| character.name == speaker)
+            .map(|index| self.characters.positions[index]);
+        let npc = || {
+            self.npcs
+                .iter()
+                .find(|npc| npc.data.name == speaker)
+                .map(|npc| npc.position)
+        };
+        let hotspot = || {
+            dialog.map(|dialog| {
+                Vec2::new(
+                    dialog.x + dialog.width / 2.0,
+                    dialog.y + dialog.height / 2.0,
+                )
+            })
+        };
+        character.or_else(npc).or_else(hotspot).map(Emitter::at)
+    }
+
     fn close_dialog_menu(&mut self) {
         self.dialog_menu.open = false;
         self.dialog_menu.current_dialog_id = None;
         self.dialog_menu.current_level = 0;
+        self.dialog_menu.close_after_lines = false;
+        self.dialog_menu.sequence_after_lines = None;
     }
 
     fn get_clicked_dialog_option(&self, game_pos: Vec2) -> Option<usize> {
-        if let Some(current_scene) = self.get_current_scene() {
-            if let Some(dialog_id) = self.dialog_menu.current_dialog_id {
-                if let Some(dialog) = current_scene.dialogs.iter().find(|d| d.id == dialog_id) {
-                    if let Some(level) = dialog.tree.get(self.dialog_menu.current_level) {
-                        // Calculate the relative mouse position within the dialog area
-                        let relative_pos = Vec2::new(
-                            game_pos.x - config::dialog::OPTION_START_X,
-                            game_pos.y - config::dialog::START_Y - config::dialog::OPTION_START_Y,
-                        );
-
-                        for (i, _) in level.options.iter().enumerate() {
-                            let option_y = i as f32 * config::dialog::OPTION_SPACING;
-                            let option_rect = Rect::new(
-                                0.0,
-                                option_y,
-                                config::dialog::OPTION_BOX_WIDTH,
-                                config::dialog::OPTION_BOX_HEIGHT,
-                            );
-
-                            if option_rect.contains(relative_pos) {
-                                return Some(i);
-                            }
-                        }
-                    }
+        if let Some(current_scene) = self.get_current_scene()
+            && let Some(dialog_id) = self.dialog_menu.current_dialog_id
+            && let Some(dialog) = current_scene.dialogs.iter().find(|d| d.id == dialog_id)
+            && let Some(level) = dialog.tree.get(self.dialog_menu.current_level)
+        {
+            // Calculate the relative mouse position within the dialog area
+            let relative_pos = Vec2::new(
+                game_pos.x - config::dialog::OPTION_START_X,
+                game_pos.y - config::dialog::START_Y - config::dialog::OPTION_START_Y,
+            );
+
+            for (i, _) in level.options.iter().enumerate() {
+                let option_y = i as f32 * config::dialog::OPTION_SPACING;
+                let option_rect = Rect::new(
+                    0.0,
+                    option_y,
+                    config::dialog::OPTION_BOX_WIDTH,
+                    config::dialog::OPTION_BOX_HEIGHT,
+                );
+
+                if option_rect.contains(relative_pos) {
+                    return Some(i);
                 }
             }
         }
@@ -1119,46 +2091,379 @@ impl Game {
 
     fn update_dialog_hover(&mut self, mouse_pos: Vec2) {
         if self.dialog_menu.open {
-            self.dialog_menu.hovered_option = self.get_clicked_dialog_option(mouse_pos);
+            self.dialog_menu.hovered_option = if self.voice_queue.is_busy() {
+                None
+            } else {
+                self.get_clicked_dialog_option(mouse_pos)
+            };
         }
     }
 
     fn handle_dialog_option_selection(&mut self, selected_option: usize) {
-        let mut audio_to_play = None;
+        let mut lines = Vec::new();
         let mut next_level = None;
+        let mut sequence = None;
 
-        if let Some(current_scene) = self.get_current_scene() {
-            if let Some(dialog_id) = self.dialog_menu.current_dialog_id {
-                if let Some(dialog) = current_scene.dialogs.iter().find(|d| d.id == dialog_id) {
-                    if let Some(level) = dialog.tree.get(self.dialog_menu.current_level) {
-                        if let Some(option) = level.options.get(selected_option) {
-                            println!("Selected option: {}", option.text);
-                            if let Some(audio) = option.response_audio.choose() {
-                                audio_to_play = Some(format!(
-                                    "voice/{}/{}_{}.wav",
-                                    current_scene.name, current_scene.name, audio
-                                ));
-                            }
-                            next_level = Some(option.target as usize);
-                        }
-                    }
-                }
-            }
+        if let Some(current_scene) = self.get_current_scene()
+            && let Some(dialog_id) = self.dialog_menu.current_dialog_id
+            && let Some(dialog) = current_scene.dialogs.iter().find(|d| d.id == dialog_id)
+            && let Some(level) = dialog.tree.get(self.dialog_menu.current_level)
+            && let Some(option) = level.options.get(selected_option)
+        {
+            log::debug!(target: logging::DIALOG, "Selected option: {}", option.text);
+            lines = option
+                .response_audio
+                .iter()
+                .enumerate()
+                .map(|(i, audio)| {
+                    let speaker = option.response_speaker(i, dialog);
+                    let mut line = VoiceLine::new(current_scene.voice_path(audio), speaker);
+                    line.emitter = self.speaker_emitter(speaker, Some(dialog));
+                    line
+                })
+                .collect();
+            next_level = Some(option.target as usize);
+            sequence = option.sequence.clone();
         }
 
         // Now that we've gathered all the information, we can modify the state
-        if let Some(audio_path) = audio_to_play {
-            self.audio_system
-                .play_audio(&self.asset_manager, &audio_path, AudioCategory::Dialog);
+        let has_lines = !lines.is_empty();
+        if has_lines {
+            self.voice_queue.play(&mut self.audio_system, lines);
         }
 
         if let Some(level) = next_level {
             self.dialog_menu.current_level = level;
         }
 
-        // Use 100 as indication that the dialog should be closed
+        // Use 100 as indication that the dialog should be closed, once the
+        // answer has been said
         if next_level == Some(100) {
+            if has_lines {
+                self.dialog_menu.close_after_lines = true;
+            } else {
+                self.close_dialog_menu();
+            }
+        }
+
+        // The sequence follows the answer, and ends the dialog
+        if has_lines {
+            self.dialog_menu.sequence_after_lines = sequence;
+        } else if let Some(sequence) = sequence {
+            self.start_sequence(&sequence);
+        }
+    }
+
+    // Starts a sequence of the current scene by name
+    fn start_sequence(&mut self, name: &str) {
+        let Some(sequence) = self
+            .get_current_scene()
+            .and_then(|scene| scene.sequences.iter().find(|s| s.name == name))
+            .cloned()
+        else {
+            log::warn!(target: logging::GAME, "No sequence named {:?} in this scene", name);
+            return;
+        };
+        if self.sequence_player.start(&sequence) {
+            log::info!(target: logging::GAME, "Playing sequence {}", name);
             self.close_dialog_menu();
+            self.inventory.drag = None;
+            for i in 0..self.characters.count {
+                self.stop_character(i);
+            }
+        }
+    }
+
+    fn play_scene_entry_sequence(&mut self) {
+        let name = self.get_current_scene().and_then(|scene| {
+            scene
+                .sequences
+                .iter()
+                .find(|sequence| sequence.on_enter)
+                .map(|sequence| sequence.name.clone())
+        });
+        if let Some(name) = name {
+            self.start_sequence(&name);
+        }
+    }
+
+    fn skip_sequence(&mut self) {
+        log::info!(
+            target: logging::GAME,
+            "Skipping sequence {}",
+            self.sequence_player.name().unwrap_or_default()
+        );
+        self.sequence_player.skip();
+        self.voice_queue.play(&mut self.audio_system, Vec::new());
+    }
+
+    // Runs the steps of the playing sequence that are due, all of them at once
+    // when it is being skipped
+    async fn update_sequence(&mut self, delta_time: f32) {
+        self.sequence_player.update(delta_time);
+        while let Some(step) = self.sequence_player.current_step().cloned() {
+            let skipping = self.sequence_player.is_skipping();
+            if self.sequence_player.begin_step() {
+                self.start_sequence_step(&step, skipping).await;
+            }
+            if !skipping && !self.is_sequence_step_done(&step) {
+                break;
+            }
+            self.sequence_player.next_step();
+        }
+    }
+
+    async fn start_sequence_step(&mut self, step: &Step, instant: bool) {
+        match step {
+            Step::Walk {
+                character, x, y, ..
+            } => {
+                let Some(index) = self.character_index(character) else {
+                    return;
+                };
+                let target = self.grid.get_grid_from_coord(Vec2::new(*x, *y));
+                let Some(target) = self
+                    .grid
+                    .closest_walkable_nodes(target, 1, 10)
+                    .ok()
+                    .and_then(|nodes| nodes.first().copied())
+                else {
+                    log::warn!(target: logging::GRID, "{} can't walk to ({}, {})", character, x, y);
+                    return;
+                };
+                if instant {
+                    self.characters.positions[index] =
+                        self.grid.get_coord_from_grid(target.0, target.1);
+                    self.stop_character(index);
+                    return;
+                }
+                let start = self
+                    .grid
+                    .get_grid_from_coord(self.characters.positions[index]);
+                match self.grid.pathfind(start, target) {
+                    Ok(path) => {
+                        self.characters.paths[index] = Some(path);
+                        self.characters.targets[index] = Some(target);
+                    }
+                    Err(e) => log::warn!(target: logging::GRID, "{}", e),
+                }
+            }
+            Step::Face {
+                character,
+                direction,
+            } => {
+                if let Some(index) = self.character_index(character) {
+                    self.characters.directions[index] = *direction;
+                }
+            }
+            Step::Say {
+                character,
+                audio,
+                text,
+                ..
+            } => {
+                if instant {
+                    return;
+                }
+                let mut line = VoiceLine::new(audio.clone().unwrap_or_default(), character);
+                line.emitter = self.speaker_emitter(character, None);
+                line.text = text.as_ref().map(|text| self.strings.get(text).to_string());
+                self.voice_queue.enqueue(line);
+            }
+            Step::Music { track } => {
+                self.music_player
+                    .play_track(track, &mut self.audio_system, &self.asset_manager);
+            }
+            Step::Sound { audio } => {
+                if instant {
+                    return;
+                }
+                if let Err(e) = self.audio_system.play_audio(
+                    &self.asset_manager,
+                    audio,
+                    AudioCategory::SoundEffect,
+                ) {
+                    log::warn!(target: logging::AUDIO, "{}", e);
+                }
+            }
+            Step::Show { texture, x, y } => {
+                let position = x.zip(*y).map(|(x, y)| Vec2::new(x, y));
+                self.sequence_player.show(texture, position);
+            }
+            Step::Hide { texture } => self.sequence_player.hide(texture.as_deref()),
+            Step::Wait { .. } => {}
+            Step::SetFlag { flag } => {
+                self.flags.insert(flag.clone());
+            }
+            Step::ClearFlag { flag } => {
+                self.flags.remove(flag);
+            }
+            Step::Video { video } => {
+                if !instant {
+                    self.play_video(video).await;
+                }
+            }
+            Step::Fade { to, seconds, .. } => {
+                let seconds = if instant { 0.0 } else { *seconds };
+                self.sequence_player.fade_to(*to, seconds);
+            }
+        }
+    }
+
+    fn is_sequence_step_done(&self, step: &Step) -> bool {
+        let elapsed = self.sequence_player.step_elapsed();
+        match step {
+            Step::Walk {
+                character,
+                wait: true,
+                ..
+            } => self
+                .character_index(character)
+                .is_none_or(|index| self.characters.paths[index].is_none()),
+            Step::Say { wait: true, .. } => !self.voice_queue.is_busy(),
+            Step::Video { .. } => !self.video_player.is_playing(),
+            Step::Wait { seconds } => elapsed >= *seconds,
+            Step::Fade {
+                seconds,
+                wait: true,
+                ..
+            } => elapsed >= *seconds,
+            _ => true,
+        }
+    }
+
+    // Starts a video by name, fading out the scene's music and ambience
+    async fn play_video(&mut self, name: &str) {
+        let Some(video) = self.videos.iter().find(|video| video.name == name).cloned() else {
+            log::warn!(target: logging::ASSETS, "No video named {:?}", name);
+            return;
+        };
+        log::info!(target: logging::GAME, "Playing video {}", name);
+
+        self.fade_out_scene_audio();
+        // The soundtrack takes the place of the music, which is faded out above.
+        // It is started as a layer so the fading tracks aren't cut off.
+        if let Some(audio) = &video.audio {
+            let result = match self.asset_manager.load_sound(audio).await {
+                Ok(()) => self
+                    .audio_system
+                    .start_layer(
+                        &self.asset_manager,
+                        audio,
+                        AudioCategory::Music,
+                        false,
+                        0.0,
+                        None,
+                    )
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            if let Err(e) = result {
+                log::warn!(target: logging::AUDIO, "{}", e);
+            }
+        }
+        self.video_player.start(video);
+    }
+
+    // Loads the frame that is due and a few after it, and drops the ones that
+    // have been shown
+    async fn update_video(&mut self) {
+        let skipped = self.input.is_pressed(Action::SkipSequence)
+            || self.input.is_pressed(Action::Pause)
+            || self.input.is_pressed(Action::MenuConfirm)
+            || is_mouse_button_pressed(MouseButton::Left);
+        let due = self.video_player.due_frame().filter(|_| !skipped);
+        if let (Some(due), Some(video)) = (due, self.video_player.video()) {
+            let last = (due + config::video::PRELOAD_FRAMES).min(video.frame_count);
+            let paths: Vec<(u32, String)> = (due..last)
+                .map(|frame| (frame, video.frame_path(frame)))
+                .collect();
+            for (frame, path) in paths {
+                match self.asset_manager.load_texture(&path).await {
+                    Ok(()) => self.video_player.mark_loaded(frame),
+                    Err(e) => log::warn!(target: logging::ASSETS, "{}", e),
+                }
+            }
+            if let Some(video) = self.video_player.video()
+                && self
+                    .asset_manager
+                    .get_texture(&video.frame_path(due))
+                    .is_some()
+            {
+                self.video_player.show(due);
+            }
+            let stale = self.video_player.take_stale_frames();
+            if let Some(video) = self.video_player.video() {
+                for frame in stale {
+                    self.asset_manager.unload_texture(&video.frame_path(frame));
+                }
+            }
+        } else {
+            self.stop_video();
+        }
+        self.audio_system.update(get_frame_time());
+    }
+
+    fn fade_out_scene_audio(&mut self) {
+        for category in [AudioCategory::Music, AudioCategory::Ambience] {
+            for playing in self.audio_system.active_sounds(category) {
+                self.audio_system
+                    .fade_out(category, &playing, config::audio::AMBIENT_FADE);
+            }
+        }
+    }
+
+    fn stop_video(&mut self) {
+        let Some((video, loaded)) = self.video_player.stop() else {
+            return;
+        };
+        log::info!(target: logging::GAME, "Video {} ended", video.name);
+        for frame in loaded {
+            self.asset_manager.unload_texture(&video.frame_path(frame));
+        }
+        if let Some(audio) = &video.audio {
+            self.audio_system.stop(AudioCategory::Music, audio);
+        }
+        if self.menu.game_started {
+            self.update_scene_audio();
+        }
+    }
+
+    fn character_index(&self, name: &str) -> Option<usize> {
+        let index = self
+            .characters
+            .data
+            .iter()
+            .position(|character| character.name == name);
+        if index.is_none() {
+            log::warn!(target: logging::GAME, "No character named {:?}", name);
+        }
+        index
+    }
+
+    fn handle_voice_events(&mut self) {
+        for event in self.voice_queue.take_events() {
+            match event {
+                VoiceEvent::LineStarted(_) => {}
+                VoiceEvent::LineFinished { line, skipped } => {
+                    log::debug!(
+                        target: logging::DIALOG,
+                        "{} finished {} (skipped: {})",
+                        line.speaker,
+                        line.path,
+                        skipped
+                    );
+                }
+                VoiceEvent::QueueFinished => {
+                    let sequence = self.dialog_menu.sequence_after_lines.take();
+                    if self.dialog_menu.close_after_lines {
+                        self.close_dialog_menu();
+                    }
+                    if let Some(sequence) = sequence {
+                        self.start_sequence(&sequence);
+                    }
+                }
+            }
         }
     }
 
@@ -1229,22 +2534,23 @@ impl Game {
             );
 
             self.grid.update_blocked_nodes(blocked_nodes);
-
-            let spawn_positions =
-                self.generate_spawn_positions(spawn_position, self.characters.count);
-
-            for (i, pos) in spawn_positions.into_iter().enumerate() {
-                if i < self.characters.count {
-                    self.characters.positions[i] = pos;
-                    self.characters.directions[i] = Direction::South;
-                    self.characters.paths[i] = None;
-                    self.characters.targets[i] = None;
+            let surfaces = self
+                .get_current_scene()
+                .map(|scene| scene.surfaces.clone())
+                .unwrap_or_default();
+            self.grid.update_surfaces(surfaces);
+
+            match self.place_characters(spawn_position) {
+                Ok(()) => self.scene_entrance = spawn_position,
+                Err(e) => {
+                    log::warn!(target: logging::GRID, "Failed to place characters: {}", e)
                 }
             }
         }
 
         self.load_current_and_adjacent_scenes().await;
         self.update_scene_audio();
+        self.play_scene_entry_sequence();
     }
 
     fn get_transition_data(
@@ -1260,19 +2566,26 @@ impl Game {
         })
     }
 
-    fn generate_spawn_positions(&self, center: Vec2, count: usize) -> Vec<Vec2> {
-        let mut positions = Vec::with_capacity(count);
+    fn generate_spawn_positions(&self, center: Vec2, count: usize) -> Result<Vec<Vec2>, GridError> {
+        let target_grid = self.grid.get_grid_from_coord(center);
+        let spawn_positions = self.grid.closest_walkable_nodes(target_grid, count, 10)?;
 
-        let spawn_positions = self.find_n_closest_walkable_grids(center, count).unwrap();
+        Ok(spawn_positions
+            .iter()
+            .map(|&(x, y)| self.grid.get_coord_from_grid(x, y))
+            .collect())
+    }
 
-        for i in 0..count {
-            let pos = self
-                .grid
-                .get_coord_from_grid(spawn_positions[i].0, spawn_positions[i].1);
-            positions.push(pos);
-        }
+    fn place_characters(&mut self, center: Vec2) -> Result<(), GridError> {
+        let spawn_positions = self.generate_spawn_positions(center, self.characters.count)?;
 
-        positions
+        for (i, pos) in spawn_positions.into_iter().enumerate() {
+            self.characters.positions[i] = pos;
+            self.characters.directions[i] = Direction::South;
+            self.characters.paths[i] = None;
+            self.characters.targets[i] = None;
+        }
+        Ok(())
     }
 
     async fn handle_pathfinding(&mut self, target_pos: Vec2) {
@@ -1308,10 +2621,10 @@ impl Game {
             }
 
             // Check if the clicked position is the same as the current target
-            if let Some(current_target) = self.characters.targets[active_index] {
-                if current_target == final_target {
-                    return;
-                }
+            if let Some(current_target) = self.characters.targets[active_index]
+                && current_target == final_target
+            {
+                return;
             }
 
             // Don't move if the player is already at the target
@@ -1323,45 +2636,17 @@ impl Game {
                 .grid
                 .get_grid_from_coord(self.characters.positions[active_index]);
 
-            if let Some(path) = self.grid.pathfind(start_grid, final_target) {
-                self.characters.paths[active_index] = Some(path);
-                self.characters.targets[active_index] = Some(final_target);
-            } else {
+            match self.grid.pathfind(start_grid, final_target) {
+                Ok(path) => {
+                    self.characters.paths[active_index] = Some(path);
+                    self.characters.targets[active_index] = Some(final_target);
+                }
                 // If no path is found, stop the character
-                self.stop_character(active_index);
-            }
-        }
-    }
-
-    fn find_n_closest_walkable_grids(&self, pixel_pos: Vec2, n: usize) -> Option<Vec<(i32, i32)>> {
-        let target_grid = self.grid.get_grid_from_coord(pixel_pos);
-        let mut walkable_grids = Vec::new();
-
-        let search_radius = 10;
-
-        for dx in -search_radius..=search_radius {
-            for dy in -search_radius..=search_radius {
-                let grid_pos = (target_grid.0 + dx, target_grid.1 + dy);
-
-                if self.grid.is_node_walkable(grid_pos) {
-                    let distance = (dx * dx + dy * dy) as f32;
-                    walkable_grids.push((grid_pos, distance));
+                Err(_) => {
+                    self.stop_character(active_index);
                 }
             }
         }
-
-        if walkable_grids.is_empty() {
-            return None;
-        }
-
-        walkable_grids.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
-        Some(
-            walkable_grids
-                .into_iter()
-                .take(n)
-                .map(|(pos, _)| pos)
-                .collect(),
-        )
     }
 
     fn find_closest_walkable_node(
@@ -1378,12 +2663,12 @@ impl Game {
         ));
 
         let mut closest_node = None;
-        let mut min_distance = std::i32::MAX;
+        let mut min_distance = i32::MAX;
 
         for x in start.0..=end.0 {
             for y in start.1..=end.1 {
                 if self.grid.is_node_walkable((x, y)) {
-                    let distance = ((x - target.0).pow(2) + (y - target.1).pow(2)) as i32;
+                    let distance = (x - target.0).pow(2) + (y - target.1).pow(2);
                     if distance < min_distance {
                         min_distance = distance;
                         closest_node = Some((x, y));
@@ -1398,125 +2683,398 @@ impl Game {
     async fn update(&mut self) {
         self.update_window_size();
 
-        let mouse_pos = Vec2::from(mouse_position());
-        let game_pos = self.get_game_coordinates(mouse_pos);
+        self.input.update();
+        self.update_cursor();
+        let game_pos = self.cursor.position;
+
+        if self.input.is_pressed(Action::ToggleFullscreen) {
+            self.toggle_fullscreen();
+        }
+        // A video holds everything else until it ends or is skipped
+        if self.video_player.is_playing() {
+            self.update_video().await;
+            return;
+        }
+        self.reveal_hotspots = !self.menu.is_open() && self.input.is_down(Action::RevealHotspots);
+        // The game is paused while a menu is open
+        if !self.update_menu(game_pos).await {
+            return;
+        }
+
+        // Sequences take control away from the player until they end or are skipped
+        if self.sequence_player.is_playing() {
+            if self.input.is_pressed(Action::SkipSequence) {
+                self.skip_sequence();
+            }
+            if self.input.is_pressed(Action::SkipLine) {
+                self.voice_queue.skip_line(&mut self.audio_system);
+            }
+            self.current_cursor = CursorType::Normal;
+            self.hover_label = None;
+        } else {
+            self.handle_player_input(game_pos).await;
+        }
+
+        let delta_time = get_frame_time();
+        self.update_sequence(delta_time).await;
+        self.update_characters(delta_time);
+        self.update_npcs(delta_time);
+        if let Some(active_index) = self.active_character {
+            self.audio_system
+                .set_listener(self.characters.positions[active_index]);
+        }
+        self.music_player
+            .update(&mut self.audio_system, &self.asset_manager);
+        self.voice_queue
+            .update(&mut self.audio_system, &self.asset_manager);
+        self.handle_voice_events();
+        self.update_talk_animation();
+        self.audio_system.update(delta_time);
+        self.update_inventory_animation(delta_time);
+        self.update_inventory(game_pos);
+        self.save_settings_if_changed();
+    }
 
-        if is_mouse_button_pressed(MouseButton::Left) {
+    // Clicks and keys that act on the scene, ignored while a sequence plays
+    async fn handle_player_input(&mut self, game_pos: Vec2) {
+        let cursor_clicked = self.input.is_pressed(Action::CursorClick);
+        if is_mouse_button_pressed(MouseButton::Left) || cursor_clicked {
             self.handle_mouse_click(game_pos).await;
         }
+        // Keyboard and gamepad clicks have no separate release
+        let released = is_mouse_button_released(MouseButton::Left) || cursor_clicked;
+        self.update_inventory_drag(game_pos, released);
 
-        if is_mouse_button_pressed(MouseButton::Right) {
+        if is_mouse_button_pressed(MouseButton::Right)
+            || self.input.is_pressed(Action::CursorRightClick)
+        {
             self.handle_right_click(game_pos);
         }
 
-        if is_key_pressed(KeyCode::D) {
-            self.debug_tools.active = !self.debug_tools.active;
+        if self.input.is_pressed(Action::SkipLine) {
+            self.voice_queue.skip_line(&mut self.audio_system);
         }
-        if is_key_pressed(KeyCode::G) {
-            if self.debug_tools.active {
-                self.debug_tools.draw_grid = !self.debug_tools.draw_grid;
-            }
+        if self.input.is_pressed(Action::ToggleInventory) {
+            self.toggle_inventory();
+        }
+        // The number keys switch levels instead while the debug level switch is on
+        if !self.debug_level_switch_mode
+            && let Some(index) = (0..self.characters.count)
+                .find(|&i| self.input.is_pressed(Action::SelectCharacter(i)))
+        {
+            self.select_character(index);
+        }
+
+        #[cfg(any(debug_assertions, feature = "debug-tools"))]
+        self.handle_debug_input().await;
+
+        // Update cursor based on game position
+        let new_cursor_type = self.determine_cursor(game_pos);
+        if new_cursor_type != self.current_cursor {
+            self.current_cursor = new_cursor_type;
+        }
+        self.hover_label = if self.dialog_menu.open || self.inventory.open {
+            None
+        } else {
+            self.describe_hotspot_at(game_pos)
+        };
+
+        self.update_dialog_hover(game_pos);
+    }
+
+    fn update_cursor(&mut self) {
+        let mouse_pos = self.get_game_coordinates(Vec2::from(mouse_position()));
+        // The cursor keys navigate the menus, only the stick moves the cursor there
+        let direction = self.input.cursor_direction(!self.menu.is_open());
+        let snap = if self.input.is_pressed(Action::NextHotspot) {
+            Some(Snap::Next)
+        } else if self.input.is_pressed(Action::PreviousHotspot) {
+            Some(Snap::Previous)
+        } else {
+            None
+        };
+        let snap_points = if snap.is_some() {
+            self.snap_points()
+        } else {
+            Vec::new()
+        };
+        self.cursor
+            .update(mouse_pos, direction, snap, &snap_points, get_frame_time());
+    }
+
+    // Points the virtual cursor snaps between: the menu buttons while a menu is
+    // open, otherwise the hotspots of the current scene
+    fn snap_points(&self) -> Vec<Vec2> {
+        if self.menu.is_open() {
+            return self
+                .menu
+                .buttons(
+                    &self.ui.menu_items,
+                    &self.audio_system,
+                    &self.strings,
+                    self.settings.fullscreen,
+                )
+                .iter()
+                .map(|button| button.rect.center())
+                .collect();
         }
+        self.hotspots()
+            .iter()
+            .map(|hotspot| hotspot.rect.center())
+            .collect()
+    }
 
-        if is_key_pressed(KeyCode::M) && self.debug_tools.active {
-            if self.audio_system.is_muted() {
-                println!("Unmuting audio");
+    // Runs the menus instead of the game while one is open. Returns false when
+    // the game should not be updated this frame.
+    async fn update_menu(&mut self, game_pos: Vec2) -> bool {
+        if self.input.is_pressed(Action::Pause) {
+            if self.menu.is_open() {
+                self.menu.back();
             } else {
-                println!("Muting audio");
+                self.menu.open(MenuScreen::Pause);
             }
-            self.audio_system.toggle_mute(&self.asset_manager);
         }
-        if is_key_pressed(KeyCode::F3) {
-            self.debug_instant_move = !self.debug_instant_move;
-            println!("Debug instant move: {}", self.debug_instant_move);
+        if !self.menu.is_open() {
+            return true;
+        }
+
+        self.current_cursor = CursorType::Normal;
+        let buttons = self.menu.buttons(
+            &self.ui.menu_items,
+            &self.audio_system,
+            &self.strings,
+            self.settings.fullscreen,
+        );
+        if buttons.is_empty() {
+            return false;
+        }
+        if self.input.is_pressed(Action::MenuDown) {
+            self.menu.selected = (self.menu.selected + 1) % buttons.len();
+        }
+        if self.input.is_pressed(Action::MenuUp) {
+            self.menu.selected = (self.menu.selected + buttons.len() - 1) % buttons.len();
+        }
+        // Only a moving cursor selects, so it doesn't fight the menu keys
+        if self.cursor.moved
+            && let Some(hovered) = buttons.iter().position(|b| b.rect.contains(game_pos))
+        {
+            self.menu.selected = hovered;
+        }
+        self.menu.selected = self.menu.selected.min(buttons.len() - 1);
+
+        let button = &buttons[self.menu.selected];
+        if let MenuAction::Volume(bus) = button.action {
+            let volume = self.audio_system.get_volume(bus);
+            if self.input.is_pressed(Action::MenuLeft) {
+                self.set_bus_volume(bus, volume - config::audio::VOLUME_STEP);
+            }
+            if self.input.is_pressed(Action::MenuRight) {
+                self.set_bus_volume(bus, volume + config::audio::VOLUME_STEP);
+            }
+        }
+
+        let clicked = (is_mouse_button_pressed(MouseButton::Left)
+            || self.input.is_pressed(Action::CursorClick))
+            && button.rect.contains(game_pos);
+        if button.enabled && (clicked || self.input.is_pressed(Action::MenuConfirm)) {
+            self.activate_menu_action(button.action).await;
         }
 
-        if is_key_pressed(KeyCode::B) {
+        let delta_time = get_frame_time();
+        self.audio_system.update(delta_time);
+        self.save_settings_if_changed();
+        false
+    }
+
+    #[cfg(any(debug_assertions, feature = "debug-tools"))]
+    async fn handle_debug_input(&mut self) {
+        if self.input.is_debug_pressed(DebugAction::ToggleTools) {
+            self.debug_tools.active = !self.debug_tools.active;
+        }
+        if self.input.is_debug_pressed(DebugAction::ToggleInstantMove) {
+            self.debug_instant_move = !self.debug_instant_move;
+            log::info!(
+                target: logging::INPUT,
+                "Debug instant move: {}",
+                self.debug_instant_move
+            );
+        }
+        if self
+            .input
+            .is_debug_pressed(DebugAction::ToggleBoundingBoxes)
+        {
             self.debug_tools.bounding_box_mode = !self.debug_tools.bounding_box_mode;
         }
+        if self.input.is_debug_pressed(DebugAction::AnimationFaster) {
+            self.set_animation_speed(
+                self.settings.animation_speed - config::settings::ANIMATION_SPEED_STEP,
+            );
+        }
+        if self.input.is_debug_pressed(DebugAction::AnimationSlower) {
+            self.set_animation_speed(
+                self.settings.animation_speed + config::settings::ANIMATION_SPEED_STEP,
+            );
+        }
+        if !self.debug_tools.active {
+            return;
+        }
 
-        // Animation speed controls
-        if is_key_pressed(KeyCode::Up) {
-            for i in 0..self.characters.count {
-                self.characters.animation_speeds[i] -= 0.01;
+        if self.input.is_debug_pressed(DebugAction::ToggleGrid) {
+            self.debug_tools.draw_grid = !self.debug_tools.draw_grid;
+        }
+        if self
+            .input
+            .is_debug_pressed(DebugAction::ToggleMissingAssets)
+        {
+            self.debug_tools.show_missing_assets = !self.debug_tools.show_missing_assets;
+        }
+        if self.input.is_debug_pressed(DebugAction::ToggleLog) {
+            self.debug_tools.show_log = !self.debug_tools.show_log;
+        }
+        if self.debug_tools.show_log {
+            // Select a log target and make it more or less verbose
+            if self.input.is_debug_pressed(DebugAction::NextLogTarget) {
+                self.debug_tools.log_target =
+                    (self.debug_tools.log_target + 1) % logging::TARGETS.len();
+            }
+            let target = logging::TARGETS[self.debug_tools.log_target];
+            if self.input.is_debug_pressed(DebugAction::LogMoreVerbose) {
+                logging::step_level(target, true);
+            }
+            if self.input.is_debug_pressed(DebugAction::LogLessVerbose) {
+                logging::step_level(target, false);
             }
         }
-        if is_key_pressed(KeyCode::Down) {
-            for i in 0..self.characters.count {
-                self.characters.animation_speeds[i] += 0.01;
-            }
-        }
-
-        if self.debug_tools.active {
-            if is_key_pressed(KeyCode::L) {
-                self.debug_level_switch_mode = !self.debug_level_switch_mode;
-            }
-
-            if self.debug_level_switch_mode {
-                for i in 0..10 {
-                    // Support up to 10 levels (0-9)
-                    if is_key_pressed(match i {
-                        0 => KeyCode::Key0,
-                        1 => KeyCode::Key1,
-                        2 => KeyCode::Key2,
-                        3 => KeyCode::Key3,
-                        4 => KeyCode::Key4,
-                        5 => KeyCode::Key5,
-                        6 => KeyCode::Key6,
-                        7 => KeyCode::Key7,
-                        8 => KeyCode::Key8,
-                        9 => KeyCode::Key9,
-                        _ => continue,
-                    }) {
-                        self.switch_to_level(i as u32).await;
-                        break;
-                    }
-                }
+
+        let buses = [
+            Bus::Master,
+            Bus::Category(AudioCategory::Music),
+            Bus::Category(AudioCategory::Dialog),
+            Bus::Category(AudioCategory::SoundEffect),
+            Bus::Category(AudioCategory::Ambience),
+        ];
+        for bus in buses {
+            if self.input.is_debug_pressed(DebugAction::ToggleMute(bus)) {
+                self.audio_system.toggle_mute(bus);
+                self.settings_changed = true;
+                log::info!(
+                    target: logging::AUDIO,
+                    "{:?} muted: {}",
+                    bus,
+                    self.audio_system.is_muted(bus)
+                );
             }
         }
-        // Update cursor based on game position
-        let new_cursor_type = self.determine_cursor(game_pos);
-        if new_cursor_type != self.current_cursor {
-            self.current_cursor = new_cursor_type;
+        let master_volume = self.audio_system.get_volume(Bus::Master);
+        if self.input.is_debug_pressed(DebugAction::VolumeDown) {
+            self.set_bus_volume(Bus::Master, master_volume - config::audio::VOLUME_STEP);
+        }
+        if self.input.is_debug_pressed(DebugAction::VolumeUp) {
+            self.set_bus_volume(Bus::Master, master_volume + config::audio::VOLUME_STEP);
         }
 
-        self.update_dialog_hover(game_pos);
+        if self.input.is_debug_pressed(DebugAction::ToggleLevelSwitch) {
+            self.debug_level_switch_mode = !self.debug_level_switch_mode;
+        }
+        if self.debug_level_switch_mode
+            && let Some(level) =
+                (0..10).find(|&i| self.input.is_debug_pressed(DebugAction::SwitchLevel(i)))
+        {
+            self.switch_to_level(level).await;
+        }
+    }
 
-        let delta_time = get_frame_time();
-        self.update_characters(delta_time);
-        self.update_inventory_animation(delta_time);
-        self.update_inventory(game_pos);
+    async fn activate_menu_action(&mut self, action: MenuAction) {
+        match action {
+            MenuAction::NewGame => self.start_new_game().await,
+            MenuAction::Continue | MenuAction::Resume => self.menu.close(),
+            MenuAction::Options => self.menu.open(MenuScreen::Options),
+            MenuAction::Quit => self.quit_requested = true,
+            MenuAction::ReturnToTitle => self.menu.return_to_title(),
+            MenuAction::Volume(bus) => {
+                // Clicking steps the volume up and wraps around to silent
+                let volume = self.audio_system.get_volume(bus) + config::audio::VOLUME_STEP;
+                self.set_bus_volume(bus, if volume > 1.0 + 0.001 { 0.0 } else { volume });
+            }
+            MenuAction::ToggleFullscreen => self.toggle_fullscreen(),
+            MenuAction::Back => self.menu.back(),
+        }
+    }
+
+    async fn start_new_game(&mut self) {
+        log::info!(target: logging::GAME, "Starting a new game");
+        self.close_dialog_menu();
+        self.voice_queue.play(&mut self.audio_system, Vec::new());
+        self.inventory = InventoryData::new();
+        self.sequence_player.reset();
+        self.flags.clear();
+        // Items go back to where the level data puts them
+        self.level_items.clear();
+        self.world_items.clear();
+        self.active_character = Some(0);
+        self.switch_to_level(0).await;
+        self.menu.game_started = true;
+        self.menu.close();
+    }
+
+    fn set_bus_volume(&mut self, bus: Bus, volume: f32) {
+        self.audio_system.set_volume(bus, volume);
+        self.settings_changed = true;
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        self.settings.fullscreen = !self.settings.fullscreen;
+        self.renderer
+            .apply_display(self.settings.fullscreen, self.settings.resolution);
+        self.settings_changed = true;
+    }
+
+    #[cfg(any(debug_assertions, feature = "debug-tools"))]
+    fn set_animation_speed(&mut self, speed: f32) {
+        let speed = speed.max(config::settings::MIN_ANIMATION_SPEED);
+        self.settings.animation_speed = speed;
+        self.characters.animation_speeds.fill(speed);
+        self.settings_changed = true;
+    }
+
+    fn save_settings_if_changed(&mut self) {
+        if !self.settings_changed {
+            return;
+        }
+        self.settings_changed = false;
+        self.settings.capture_audio(&self.audio_system);
+        if let Err(e) = self.settings.save() {
+            log::warn!(target: logging::GAME, "{}", e);
+        }
     }
 
     async fn switch_to_level(&mut self, level_index: u32) {
         if level_index < self.levels.len() as u32 {
+            self.store_level_items();
             self.current_level = level_index;
             self.load_level_scenes(self.current_level);
             self.current_scene = 0; // Reset to the first scene of the new level
             self.load_current_and_adjacent_scenes().await;
+            self.update_scene_audio();
+            self.play_scene_entry_sequence();
 
-            let spawn_position = Vec2::new(1000.0, 800.0); // Default spawn position
-            let spawn_positions =
-                self.generate_spawn_positions(spawn_position, self.characters.count);
-            for (i, pos) in spawn_positions.into_iter().enumerate() {
-                if i < self.characters.count {
-                    self.characters.positions[i] = pos;
-                    self.characters.directions[i] = Direction::South;
-                    self.characters.paths[i] = None;
-                    self.characters.targets[i] = None;
-                }
+            self.scene_entrance = character::DEFAULT_SPAWN;
+            if let Err(e) = self.place_characters(character::DEFAULT_SPAWN) {
+                log::warn!(target: logging::GRID, "Failed to place characters: {}", e);
             }
 
-            println!(
+            log::info!(
+                target: logging::GAME,
                 "Switched to level: {}",
                 self.levels[level_index as usize].name
             );
         } else {
-            println!("Invalid level index: {}", level_index);
+            log::warn!(target: logging::GAME, "Invalid level index: {}", level_index);
         }
     }
 
     fn update_characters(&mut self, delta_time: f32) {
+        let mut footsteps = Vec::new();
         for i in 0..self.characters.count {
             if let Some(path) = &mut self.characters.paths[i] {
                 if !path.is_empty() {
@@ -1539,11 +3097,21 @@ impl Game {
                     }
 
                     // Update animation
+                    self.characters.animation_states[i] = AnimationState::Walking;
                     self.characters.animation_timers[i] += delta_time;
                     if self.characters.animation_timers[i] >= self.characters.animation_speeds[i] {
                         self.characters.animation_timers[i] -= self.characters.animation_speeds[i];
-                        self.characters.animation_indices[i] =
-                            (self.characters.animation_indices[i] + 1) % 8;
+                        let frame = (self.characters.animation_indices[i] + 1) % 8;
+                        self.characters.animation_indices[i] = frame;
+
+                        let step_frames: &[usize] = if self.characters.is_running[i] {
+                            &character::RUN_STEP_FRAMES
+                        } else {
+                            &character::WALK_STEP_FRAMES
+                        };
+                        if step_frames.contains(&frame) {
+                            footsteps.push(i);
+                        }
                     }
 
                     // Check if character has reached the current path node
@@ -1560,6 +3128,33 @@ impl Game {
                 self.reset_character_animation(i);
             }
         }
+
+        for index in footsteps {
+            self.play_footstep(index);
+        }
+    }
+
+    fn play_footstep(&mut self, index: usize) {
+        let position = self.characters.positions[index];
+        let Some(surface) = self
+            .grid
+            .surface_at(self.grid.get_grid_from_coord(position))
+        else {
+            return;
+        };
+        if let Some(audio_file) = self
+            .footsteps
+            .get(&surface)
+            .and_then(|files| files.choose())
+            && let Err(e) = self.audio_system.play_one_shot(
+                &self.asset_manager,
+                audio_file,
+                AudioCategory::SoundEffect,
+                Some(Emitter::at(position)),
+            )
+        {
+            log::warn!(target: logging::AUDIO, "{}", e);
+        }
     }
 
     fn stop_character(&mut self, index: usize) {
@@ -1572,6 +3167,138 @@ impl Game {
     fn reset_character_animation(&mut self, index: usize) {
         self.characters.animation_indices[index] = 0;
         self.characters.animation_timers[index] = 0.0;
+        self.characters.animation_states[index] = AnimationState::Idle;
+    }
+
+    // Characters that stand still while their voice line plays get the talk
+    // state, driven by the loudness of the clip. NPCs do the same in their
+    // own update.
+    fn update_talk_animation(&mut self) {
+        for i in 0..self.characters.count {
+            if self.characters.animation_states[i] == AnimationState::Walking {
+                continue;
+            }
+            self.characters.animation_states[i] =
+                match self.talk_amplitude(&self.characters.data[i].name) {
+                    Some(amplitude) => AnimationState::Talking(amplitude),
+                    None => AnimationState::Idle,
+                };
+        }
+    }
+
+    // How wide the speaker's mouth is open, None when they aren't speaking
+    fn talk_amplitude(&self, speaker: &str) -> Option<f32> {
+        let (line, elapsed) = self.voice_queue.current_line()?;
+        if line.speaker != speaker {
+            return None;
+        }
+        let amplitude = self
+            .asset_manager
+            .get_sound_amplitude(&line.path, elapsed)
+            .unwrap_or_else(|| {
+                (elapsed * character::TALK_FALLBACK_RATE * std::f32::consts::PI)
+                    .sin()
+                    .abs()
+            });
+        Some(amplitude)
+    }
+
+    // Brings the NPCs of the current scene in line with their schedules, then
+    // moves and animates them
+    fn update_npcs(&mut self, delta_time: f32) {
+        let mut previous = std::mem::take(&mut self.npcs);
+        if let Some(scene) = self.scenes.data.get(self.current_scene as usize) {
+            for data in &scene.npcs {
+                let Some(entry) = data.schedule_entry(&self.flags) else {
+                    continue;
+                };
+                let existing = previous.iter().position(|npc| {
+                    npc.scene == scene.id && npc.data.name == data.name && npc.entry == entry
+                });
+                self.npcs.push(match existing {
+                    Some(index) => previous.swap_remove(index),
+                    None => Npc::new(data.clone(), scene.id, entry),
+                });
+            }
+        }
+
+        let talking: Vec<Option<f32>> = self
+            .npcs
+            .iter()
+            .map(|npc| self.talk_amplitude(&npc.data.name))
+            .collect();
+        for (npc, talking) in self.npcs.iter_mut().zip(talking) {
+            npc.update(
+                &self.grid,
+                talking,
+                self.settings.animation_speed,
+                delta_time,
+            );
+        }
+        self.update_guards(delta_time);
+    }
+
+    // Guards watch the gang unless a cutscene or conversation is going on
+    fn update_guards(&mut self, delta_time: f32) {
+        if self.sequence_player.is_playing() || self.dialog_menu.open {
+            return;
+        }
+        let positions = &self.characters.positions[..self.characters.count];
+        let mut spotted_by = None;
+        for (index, npc) in self.npcs.iter_mut().enumerate() {
+            if npc.watch(&self.grid, positions, delta_time) && spotted_by.is_none() {
+                spotted_by = Some(index);
+            }
+        }
+        if let Some(index) = spotted_by {
+            self.guard_caught_gang(index);
+        }
+    }
+
+    // Stops everything the game had going and goes back to the title screen,
+    // where only a new game can be started
+    fn game_over(&mut self) {
+        log::info!(target: logging::GAME, "Game over");
+        self.menu.game_started = false;
+        self.sequence_player.stop();
+        self.stop_video();
+        self.close_dialog_menu();
+        self.voice_queue.play(&mut self.audio_system, Vec::new());
+        self.fade_out_scene_audio();
+        self.inventory = InventoryData::new();
+        self.npcs.clear();
+        for i in 0..self.characters.count {
+            self.stop_character(i);
+        }
+        self.menu.return_to_title();
+    }
+
+    fn guard_caught_gang(&mut self, index: usize) {
+        let npc = &self.npcs[index];
+        let Some(guard) = &npc.data.guard else {
+            return;
+        };
+        let consequence = guard.consequence.clone();
+        log::info!(target: logging::GAME, "{} spotted the gang", npc.data.name);
+        let mut line = VoiceLine::new("", npc.data.name.as_str());
+        line.text = Some(self.strings.get("guard.caught").to_string());
+
+        for npc in &mut self.npcs {
+            npc.detection = 0.0;
+        }
+        match consequence {
+            Consequence::ResetToEntrance => {
+                self.voice_queue.play(&mut self.audio_system, vec![line]);
+                if let Err(e) = self.place_characters(self.scene_entrance) {
+                    log::warn!(target: logging::GRID, "Failed to place characters: {}", e);
+                }
+            }
+            Consequence::GameOver => self.game_over(),
+            Consequence::Dialog { dialog } => {
+                self.voice_queue.play(&mut self.audio_system, vec![line]);
+                self.open_dialog(dialog);
+            }
+        }
     }
 
     fn draw(&self) {
@@ -1589,15 +3316,148 @@ impl Game {
 
 #[macroquad::main("OpenJönsson")]
 async fn main() {
+    logging::init();
     show_mouse(false);
-    match Game::new().await {
-        Ok(mut game) => loop {
-            game.update().await;
-            game.draw();
-            next_frame().await
-        },
+    let resolver = AssetResolver::from_environment();
+    for root in resolver.roots() {
+        log::info!(
+            target: logging::ASSETS,
+            "Asset search root: {} ({})",
+            root.path.display(),
+            root.source
+        );
+    }
+    match Game::new(resolver).await {
+        Ok(mut game) => {
+            while !game.quit_requested {
+                game.update().await;
+                game.draw();
+                next_frame().await
+            }
+        }
         Err(e) => {
-            eprintln!("Failed to initialize game: {}", e);
+            log::error!(target: logging::GAME, "Failed to initialize game: {}", e);
+            show_error_screen(&format!("Failed to initialize game:\n{}", e)).await;
+        }
+    }
+}
+
+// Keeps the window open with the error until it is closed or Escape is pressed
+async fn show_error_screen(message: &str) {
+    show_mouse(true);
+    let mut renderer = Renderer::new(Vec2::new(screen_width(), screen_height()));
+    while !is_key_pressed(KeyCode::Escape) {
+        renderer.update_window_size(Vec2::new(screen_width(), screen_height()));
+        clear_background(BLACK);
+        renderer.draw_error_message(message);
+        next_frame().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inventory_with(items: &[u32]) -> InventoryData {
+        let mut inventory = InventoryData::new();
+        for &item in items {
+            inventory.add(0, item);
+        }
+        inventory
+    }
+
+    #[test]
+    fn empty_inventory_has_no_items() {
+        let mut inventory = InventoryData::new();
+        assert!(inventory.shown().is_empty());
+        assert_eq!(inventory.item_in_slot(0), None);
+        assert_eq!(inventory.remove(0), None);
+        assert!(!inventory.is_full(0));
+    }
+
+    #[test]
+    fn add_stops_at_inventory_size() {
+        let mut inventory = InventoryData::new();
+        for item in 0..inventory::INVENTORY_SIZE as u32 {
+            assert!(inventory.add(0, item));
         }
+        assert!(inventory.is_full(0));
+        assert!(!inventory.add(0, 99));
+        assert!(inventory.add(1, 99));
+    }
+
+    #[test]
+    fn inventories_are_per_character() {
+        let mut inventory = inventory_with(&[1, 2]);
+        inventory.add(2, 3);
+        assert_eq!(inventory.shown(), &[1, 2]);
+        inventory.show(2);
+        assert_eq!(inventory.shown(), &[3]);
+        inventory.show(1);
+        assert!(inventory.shown().is_empty());
+    }
+
+    #[test]
+    fn remove_out_of_range_keeps_items() {
+        let mut inventory = inventory_with(&[1, 2]);
+        assert_eq!(inventory.remove(2), None);
+        assert_eq!(inventory.remove(0), Some(1));
+        assert_eq!(inventory.shown(), &[2]);
+    }
+
+    #[test]
+    fn combine_puts_result_in_target_slot() {
+        let mut inventory = inventory_with(&[1, 2, 3]);
+        inventory.combine(0, 2, 9);
+        assert_eq!(inventory.shown(), &[2, 9]);
+        assert_eq!(inventory.combined, Some(1));
+
+        let mut inventory = inventory_with(&[1, 2, 3]);
+        inventory.combine(2, 0, 9);
+        assert_eq!(inventory.shown(), &[9, 2]);
+        assert_eq!(inventory.combined, Some(0));
+    }
+
+    #[test]
+    fn combine_ignores_same_or_missing_slots() {
+        let mut inventory = inventory_with(&[1, 2]);
+        inventory.combine(1, 1, 9);
+        inventory.combine(0, 5, 9);
+        inventory.combine(5, 0, 9);
+        assert_eq!(inventory.shown(), &[1, 2]);
+        assert_eq!(inventory.combined, None);
+    }
+
+    #[test]
+    fn move_item_shifts_items_in_between() {
+        let mut inventory = inventory_with(&[1, 2, 3, 4]);
+        inventory.move_item(0, 2);
+        assert_eq!(inventory.shown(), &[2, 3, 1, 4]);
+        inventory.move_item(3, 0);
+        assert_eq!(inventory.shown(), &[4, 2, 3, 1]);
+    }
+
+    #[test]
+    fn move_item_past_the_end_moves_to_last_slot() {
+        let mut inventory = inventory_with(&[1, 2, 3]);
+        inventory.move_item(0, 10);
+        assert_eq!(inventory.shown(), &[2, 3, 1]);
+        inventory.move_item(10, 0);
+        assert_eq!(inventory.shown(), &[2, 3, 1]);
+    }
+
+    #[test]
+    fn scroll_stays_within_items() {
+        let items: Vec<u32> = (0..inventory::SLOT_COUNT as u32 + 2).collect();
+        let mut inventory = inventory_with(&items);
+        inventory.scroll_by(10);
+        assert_eq!(inventory.scroll_offset, 2);
+        assert_eq!(inventory.item_in_slot(0), Some(2));
+        inventory.scroll_by(-10);
+        assert_eq!(inventory.scroll_offset, 0);
+
+        inventory.scroll_by(2);
+        inventory.remove(0);
+        assert_eq!(inventory.scroll_offset, 1);
     }
 }
@@ -0,0 +1,198 @@
+use crate::MenuItem;
+use crate::audio::{AudioCategory, AudioSystem, Bus};
+use crate::config::menu;
+use crate::strings::Strings;
+use macroquad::prelude::Rect;
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MenuScreen {
+    Title,
+    Pause,
+    Options,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuAction {
+    NewGame,
+    Continue,
+    Options,
+    Quit,
+    Resume,
+    ReturnToTitle,
+    Volume(Bus),
+    ToggleFullscreen,
+    Back,
+}
+
+impl MenuAction {
+    // Name used for the action's `MenuItem` in level_data.json
+    fn item_name(&self) -> &'static str {
+        match self {
+            MenuAction::NewGame => "new_game",
+            MenuAction::Continue => "continue",
+            MenuAction::Options => "options",
+            MenuAction::Quit => "quit",
+            MenuAction::Resume => "resume",
+            MenuAction::ReturnToTitle => "return_to_title",
+            MenuAction::Volume(Bus::Master) => "volume_master",
+            MenuAction::Volume(Bus::Category(AudioCategory::Music)) => "volume_music",
+            MenuAction::Volume(Bus::Category(AudioCategory::Ambience)) => "volume_ambience",
+            MenuAction::Volume(Bus::Category(AudioCategory::Dialog)) => "volume_dialog",
+            MenuAction::Volume(Bus::Category(AudioCategory::SoundEffect)) => "volume_sfx",
+            MenuAction::ToggleFullscreen => "fullscreen",
+            MenuAction::Back => "back",
+        }
+    }
+
+    // Key of the button text in the strings table, e.g. "menu.new_game"
+    fn label_key(&self) -> String {
+        format!("menu.{}", self.item_name())
+    }
+}
+
+pub struct MenuButton {
+    pub action: MenuAction,
+    pub label: String,
+    pub rect: Rect,
+    // Texture from the matching `MenuItem`, buttons without one are drawn as text
+    pub texture: Option<String>,
+    pub enabled: bool,
+}
+
+// Title, pause and options screens. Screens are kept on a stack so Back returns
+// to whichever screen opened the options; an empty stack means the game is running.
+pub struct Menu {
+    stack: Vec<MenuScreen>,
+    pub selected: usize,
+    // A game has been started this session, so Continue can return to it
+    pub game_started: bool,
+}
+
+impl Menu {
+    pub fn new() -> Self {
+        Menu {
+            stack: vec![MenuScreen::Title],
+            selected: 0,
+            game_started: false,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.stack.is_empty()
+    }
+
+    // The title screen or a screen opened from it, drawn without the game behind it
+    pub fn is_on_title(&self) -> bool {
+        self.stack.first() == Some(&MenuScreen::Title)
+    }
+
+    pub fn current(&self) -> Option<MenuScreen> {
+        self.stack.last().copied()
+    }
+
+    pub fn open(&mut self, screen: MenuScreen) {
+        self.stack.push(screen);
+        self.selected = 0;
+    }
+
+    // Goes back one screen. The title screen can't be left this way.
+    pub fn back(&mut self) {
+        if self.current() != Some(MenuScreen::Title) {
+            self.stack.pop();
+            self.selected = 0;
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.stack.clear();
+        self.selected = 0;
+    }
+
+    pub fn return_to_title(&mut self) {
+        self.stack = vec![MenuScreen::Title];
+        self.selected = 0;
+    }
+
+    pub fn buttons(
+        &self,
+        menu_items: &[MenuItem],
+        audio_system: &AudioSystem,
+        strings: &Strings,
+        fullscreen: bool,
+    ) -> Vec<MenuButton> {
+        let Some(screen) = self.current() else {
+            return Vec::new();
+        };
+        let actions: Vec<MenuAction> = match screen {
+            MenuScreen::Title => vec![
+                MenuAction::NewGame,
+                MenuAction::Continue,
+                MenuAction::Options,
+                MenuAction::Quit,
+            ],
+            MenuScreen::Pause => vec![
+                MenuAction::Resume,
+                MenuAction::Options,
+                MenuAction::ReturnToTitle,
+                MenuAction::Quit,
+            ],
+            MenuScreen::Options => {
+                let mut actions = vec![MenuAction::Volume(Bus::Master)];
+                actions.extend(
+                    AudioCategory::ALL
+                        .iter()
+                        .map(|&category| MenuAction::Volume(Bus::Category(category))),
+                );
+                actions.extend([MenuAction::ToggleFullscreen, MenuAction::Back]);
+                actions
+            }
+        };
+
+        actions
+            .into_iter()
+            .enumerate()
+            .filter(|(_, action)| *action != MenuAction::Quit || cfg!(not(target_arch = "wasm32")))
+            .map(|(i, action)| {
+                let item = menu_items
+                    .iter()
+                    .find(|item| item.screen == Some(screen) && item.name == action.item_name());
+                let rect = match item {
+                    Some(item) => Rect::new(
+                        item.position[0],
+                        item.position[1],
+                        item.size[0],
+                        item.size[1],
+                    ),
+                    None => Rect::new(
+                        (1920.0 - menu::BUTTON_WIDTH) / 2.0,
+                        menu::START_Y + i as f32 * menu::BUTTON_SPACING,
+                        menu::BUTTON_WIDTH,
+                        menu::BUTTON_HEIGHT,
+                    ),
+                };
+                let key = action.label_key();
+                let text = strings.get(&key);
+                let label = match action {
+                    MenuAction::Volume(bus) => {
+                        let volume = (audio_system.get_volume(bus) * 100.0).round();
+                        format!("{}: {}%", text, volume)
+                    }
+                    MenuAction::ToggleFullscreen => {
+                        let state = if fullscreen { "menu.on" } else { "menu.off" };
+                        format!("{}: {}", text, strings.get(state))
+                    }
+                    _ => text.to_string(),
+                };
+                MenuButton {
+                    action,
+                    label,
+                    rect,
+                    texture: item.map(|item| item.texture.clone()),
+                    enabled: action != MenuAction::Continue || self.game_started,
+                }
+            })
+            .collect()
+    }
+}
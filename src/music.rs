@@ -0,0 +1,262 @@
+use crate::Scene;
+use crate::asset_manager::AssetManager;
+use crate::audio::{AudioCategory, AudioSystem};
+use crate::config::audio;
+use crate::logging;
+
+// Decides what plays on the music and ambience buses as the player moves
+// between scenes. Tracks are crossfaded rather than cut, and a track keeps
+// playing when the next scene uses it too or belongs to the same area.
+pub struct MusicPlayer {
+    playlist: Vec<String>,
+    index: usize,
+    area: Option<String>,
+    crossfade: f32,
+}
+
+impl MusicPlayer {
+    pub fn new() -> Self {
+        MusicPlayer {
+            playlist: Vec::new(),
+            index: 0,
+            area: None,
+            crossfade: audio::MUSIC_CROSSFADE,
+        }
+    }
+
+    pub fn enter_scene(
+        &mut self,
+        scene: &Scene,
+        audio_system: &mut AudioSystem,
+        asset_manager: &AssetManager,
+    ) {
+        self.crossfade = scene.music_crossfade.unwrap_or(audio::MUSIC_CROSSFADE);
+        self.update_layers(scene, audio_system, asset_manager);
+
+        let tracks = scene.music_tracks();
+        let current = self.current_track().map(str::to_string);
+        let same_area = scene.music_area.is_some() && scene.music_area == self.area;
+        self.area = scene.music_area.clone();
+
+        let current = current.filter(|c| audio_system.is_playing(AudioCategory::Music, c));
+        let fading_out = current
+            .as_ref()
+            .is_some_and(|c| audio_system.is_fading_out(AudioCategory::Music, c));
+        match scene_music(&tracks, current.as_deref(), fading_out, same_area) {
+            SceneMusic::KeepTrack(index) => {
+                // The new scene shares the track, carry on from where it is
+                self.playlist = tracks;
+                self.index = index;
+            }
+            SceneMusic::KeepArea => {
+                log::debug!(
+                    target: logging::AUDIO,
+                    "Keeping {:?} playing in area {:?}",
+                    current,
+                    self.area
+                );
+            }
+            SceneMusic::Restart => {
+                // A track that was fading out is faded back in by start_layer
+                self.playlist = tracks;
+                self.index = 0;
+                self.crossfade_to_current(audio_system, asset_manager);
+            }
+        }
+    }
+
+    // Crossfades to a single looping track until the next scene is entered
+    pub fn play_track(
+        &mut self,
+        track: &str,
+        audio_system: &mut AudioSystem,
+        asset_manager: &AssetManager,
+    ) {
+        if self.current_track() == Some(track) {
+            return;
+        }
+        self.playlist = vec![track.to_string()];
+        self.index = 0;
+        self.area = None;
+        self.crossfade_to_current(audio_system, asset_manager);
+    }
+
+    // Starts the next playlist track early enough to crossfade into it.
+    // Should be called once per frame.
+    pub fn update(&mut self, audio_system: &mut AudioSystem, asset_manager: &AssetManager) {
+        if self.playlist.len() < 2 {
+            return;
+        }
+        let Some(current) = self.current_track() else {
+            return;
+        };
+        let remaining = audio_system.remaining_time(AudioCategory::Music, current);
+        if remaining.is_some_and(|r| r <= self.crossfade) {
+            self.index = (self.index + 1) % self.playlist.len();
+            self.crossfade_to_current(audio_system, asset_manager);
+        }
+    }
+
+    fn current_track(&self) -> Option<&str> {
+        self.playlist.get(self.index).map(String::as_str)
+    }
+
+    fn crossfade_to_current(
+        &mut self,
+        audio_system: &mut AudioSystem,
+        asset_manager: &AssetManager,
+    ) {
+        let next = self.current_track().map(str::to_string);
+        for playing in audio_system.active_sounds(AudioCategory::Music) {
+            if Some(&playing) != next.as_ref() {
+                audio_system.fade_out(AudioCategory::Music, &playing, self.crossfade);
+            }
+        }
+
+        let Some(next) = next else {
+            return;
+        };
+        let looped = loops_by_itself(self.playlist.len(), asset_manager.get_sound_duration(&next));
+        if let Err(e) = audio_system.start_layer(
+            asset_manager,
+            &next,
+            AudioCategory::Music,
+            looped,
+            self.crossfade,
+            None,
+        ) {
+            log::warn!(target: logging::AUDIO, "{}", e);
+        }
+    }
+
+    fn update_layers(
+        &self,
+        scene: &Scene,
+        audio_system: &mut AudioSystem,
+        asset_manager: &AssetManager,
+    ) {
+        for playing in audio_system.active_sounds(AudioCategory::Ambience) {
+            let in_scene = scene.ambient_layers.contains(&playing)
+                || scene.ambient_sources.iter().any(|s| s.audio == playing);
+            if !in_scene {
+                audio_system.fade_out(AudioCategory::Ambience, &playing, audio::AMBIENT_FADE);
+            }
+        }
+        for layer in &scene.ambient_layers {
+            if let Err(e) = audio_system.start_layer(
+                asset_manager,
+                layer,
+                AudioCategory::Ambience,
+                true,
+                audio::AMBIENT_FADE,
+                None,
+            ) {
+                log::warn!(target: logging::AUDIO, "{}", e);
+            }
+        }
+        for source in &scene.ambient_sources {
+            if let Err(e) = audio_system.start_layer(
+                asset_manager,
+                &source.audio,
+                AudioCategory::Ambience,
+                true,
+                audio::AMBIENT_FADE,
+                Some(source.emitter()),
+            ) {
+                log::warn!(target: logging::AUDIO, "{}", e);
+            }
+        }
+    }
+}
+
+// A single track loops by itself, and playlists advance in update(). That needs
+// the length of the track, so a track of unknown length loops too rather than
+// stopping with nothing after it.
+fn loops_by_itself(playlist_len: usize, duration: Option<f32>) -> bool {
+    playlist_len == 1 || duration.is_none()
+}
+
+#[derive(Debug, PartialEq)]
+enum SceneMusic {
+    // Carry on with the playing track, found at this index of the new playlist
+    KeepTrack(usize),
+    // Keep the playing track although the scene doesn't list it
+    KeepArea,
+    Restart,
+}
+
+// A track that is fading out, e.g. after game over or a video, is about to go
+// silent, so it is never carried over into the next scene
+fn scene_music(
+    tracks: &[String],
+    current: Option<&str>,
+    fading_out: bool,
+    same_area: bool,
+) -> SceneMusic {
+    let Some(current) = current.filter(|_| !fading_out) else {
+        return SceneMusic::Restart;
+    };
+    if let Some(index) = tracks.iter().position(|t| t == current) {
+        SceneMusic::KeepTrack(index)
+    } else if same_area {
+        SceneMusic::KeepArea
+    } else {
+        SceneMusic::Restart
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracks(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn single_track_loops() {
+        assert!(loops_by_itself(1, Some(120.0)));
+        assert!(loops_by_itself(1, None));
+    }
+
+    #[test]
+    fn playlist_track_of_known_length_advances() {
+        assert!(!loops_by_itself(3, Some(120.0)));
+    }
+
+    #[test]
+    fn playlist_track_of_unknown_length_loops() {
+        assert!(loops_by_itself(3, None));
+    }
+
+    #[test]
+    fn shared_track_carries_on() {
+        let tracks = tracks(&["a.wav", "b.wav"]);
+        let music = scene_music(&tracks, Some("b.wav"), false, false);
+        assert_eq!(music, SceneMusic::KeepTrack(1));
+    }
+
+    #[test]
+    fn fading_track_restarts() {
+        let tracks = tracks(&["a.wav"]);
+        assert_eq!(
+            scene_music(&tracks, Some("a.wav"), true, false),
+            SceneMusic::Restart
+        );
+        assert_eq!(
+            scene_music(&tracks, Some("b.wav"), true, true),
+            SceneMusic::Restart
+        );
+    }
+
+    #[test]
+    fn area_track_keeps_playing() {
+        let tracks = tracks(&["a.wav"]);
+        let music = scene_music(&tracks, Some("b.wav"), false, true);
+        assert_eq!(music, SceneMusic::KeepArea);
+        assert_eq!(
+            scene_music(&tracks, Some("b.wav"), false, false),
+            SceneMusic::Restart
+        );
+    }
+}
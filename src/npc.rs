@@ -0,0 +1,368 @@
+use crate::config::{character, guard, npc};
+use crate::grid::Grid;
+use crate::{AnimationState, Descriptions, Direction, Game, TalkFrames, sprite_frame_path};
+use macroquad::prelude::{Rect, Vec2};
+use serde::Deserialize;
+use std::collections::HashSet;
+
+// A character in a scene that the player doesn't control, like Doris. NPCs
+// use the same frame naming as the gang and can be clicked to open a dialog.
+#[derive(Deserialize, Debug, Clone)]
+pub struct NpcData {
+    // Also the speaker name of the dialog lines they say, e.g. "Doris"
+    pub name: String,
+    // Frame name prefix, `doris` for `Huvudmeny/Gubbar/doris1007.png`
+    pub sprite: String,
+    #[serde(default = "default_speed")]
+    pub speed: f32,
+    #[serde(default)]
+    pub idle_frames: Option<IdleFrames>,
+    #[serde(default)]
+    pub talk_frames: Option<TalkFrames>,
+    // Id of the scene dialog opened by clicking the NPC
+    #[serde(default)]
+    pub dialog: Option<u32>,
+    #[serde(default)]
+    pub descriptions: Descriptions,
+    // Makes the NPC a guard that watches for the gang
+    #[serde(default)]
+    pub guard: Option<GuardData>,
+    // Where the NPC is depending on the game flags. The first entry whose
+    // conditions hold is used, and the NPC is away when none of them do.
+    pub schedule: Vec<ScheduleEntry>,
+}
+
+fn default_speed() -> f32 {
+    npc::DEFAULT_SPEED
+}
+
+// What a guard sees and what happens when they have seen the gang for long
+// enough
+#[derive(Deserialize, Debug, Clone)]
+pub struct GuardData {
+    // How far the guard sees, in game coordinates
+    #[serde(default = "default_range")]
+    pub range: f32,
+    // Width of the vision cone in degrees
+    #[serde(default = "default_angle")]
+    pub angle: f32,
+    // Seconds a character must be in sight to fill the detection meter
+    #[serde(default = "default_detection_time")]
+    pub detection_time: f32,
+    #[serde(default)]
+    pub consequence: Consequence,
+}
+
+fn default_range() -> f32 {
+    guard::RANGE
+}
+
+fn default_angle() -> f32 {
+    guard::ANGLE
+}
+
+fn default_detection_time() -> f32 {
+    guard::DETECTION_TIME
+}
+
+impl GuardData {
+    // The two edges of the cone when looking along `facing`
+    pub fn cone_edges(&self, facing: Vec2) -> (Vec2, Vec2) {
+        let half = (self.angle / 2.0).to_radians();
+        (
+            Vec2::from_angle(-half).rotate(facing) * self.range,
+            Vec2::from_angle(half).rotate(facing) * self.range,
+        )
+    }
+
+    // Whether a point is inside the cone, ignoring walls
+    fn in_cone(&self, eye: Vec2, facing: Vec2, point: Vec2) -> bool {
+        let offset = point - eye;
+        let distance = offset.length();
+        if distance > self.range {
+            return false;
+        }
+        distance == 0.0 || facing.angle_between(offset).abs() <= (self.angle / 2.0).to_radians()
+    }
+}
+
+// What happens when a guard has spotted the gang
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Consequence {
+    // The gang is sent back to where they entered the scene
+    #[default]
+    ResetToEntrance,
+    // Back to the title screen
+    GameOver,
+    // Opens a scene dialog, e.g. to talk their way out of it
+    Dialog {
+        dialog: u32,
+    },
+}
+
+// Frames played in a loop while standing, under their own state number
+#[derive(Deserialize, Debug, Clone)]
+pub struct IdleFrames {
+    pub state: usize,
+    pub count: usize,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ScheduleEntry {
+    // Flags that must be set
+    #[serde(default)]
+    pub requires: Vec<String>,
+    // Flags that must not be set
+    #[serde(default)]
+    pub unless: Vec<String>,
+    pub x: f32,
+    pub y: f32,
+    #[serde(default = "default_direction")]
+    pub direction: Direction,
+    // Positions walked between in a loop, starting from x, y
+    #[serde(default)]
+    pub patrol: Vec<[f32; 2]>,
+    // Seconds to stand still at each patrol point
+    #[serde(default)]
+    pub pause: f32,
+}
+
+fn default_direction() -> Direction {
+    Direction::South
+}
+
+impl ScheduleEntry {
+    fn applies(&self, flags: &HashSet<String>) -> bool {
+        self.requires.iter().all(|flag| flags.contains(flag))
+            && !self.unless.iter().any(|flag| flags.contains(flag))
+    }
+}
+
+impl NpcData {
+    pub fn schedule_entry(&self, flags: &HashSet<String>) -> Option<usize> {
+        self.schedule.iter().position(|entry| entry.applies(flags))
+    }
+
+    pub fn frame_path(&self, direction: u8, frame: usize, state: usize) -> String {
+        sprite_frame_path(&self.sprite, direction, frame, state)
+    }
+
+    // Every frame the NPC can show: walking, idle and talking in all directions
+    pub fn texture_paths(&self) -> Vec<String> {
+        let mut paths = Vec::new();
+        for direction in 1..=8 {
+            for frame in 0..4 {
+                for state in [0, 7] {
+                    paths.push(self.frame_path(direction, frame, state));
+                }
+            }
+            let extra = [
+                self.idle_frames
+                    .as_ref()
+                    .map(|idle| (idle.state, idle.count)),
+                self.talk_frames
+                    .as_ref()
+                    .map(|talk| (talk.state, talk.count)),
+            ];
+            for (state, count) in extra.into_iter().flatten() {
+                paths.extend((0..count).map(|frame| self.frame_path(direction, frame, state)));
+            }
+        }
+        paths
+    }
+}
+
+// An NPC present in the current scene
+pub struct Npc {
+    pub data: NpcData,
+    pub scene: u32,
+    // Index of the schedule entry in effect
+    pub entry: usize,
+    pub position: Vec2,
+    pub direction: Direction,
+    pub animation_state: AnimationState,
+    pub animation_index: usize,
+    animation_timer: f32,
+    path: Option<Vec<(i32, i32)>>,
+    // Next patrol point, 0 being the entry's own position
+    waypoint: usize,
+    pause: f32,
+    // How close a guard is to spotting the gang, from 0 to 1
+    pub detection: f32,
+}
+
+impl Npc {
+    pub fn new(data: NpcData, scene: u32, entry: usize) -> Self {
+        let start = &data.schedule[entry];
+        Npc {
+            position: Vec2::new(start.x, start.y),
+            direction: start.direction,
+            data,
+            scene,
+            entry,
+            animation_state: AnimationState::Idle,
+            animation_index: 0,
+            animation_timer: 0.0,
+            path: None,
+            waypoint: 0,
+            pause: 0.0,
+            detection: 0.0,
+        }
+    }
+
+    pub fn schedule_entry(&self) -> &ScheduleEntry {
+        &self.data.schedule[self.entry]
+    }
+
+    // Hitbox, the same size as the gang's
+    pub fn rect(&self) -> Rect {
+        Rect::new(
+            self.position.x + character::X_OFFSET - character::WIDTH / 2.0,
+            self.position.y + character::Y_OFFSET - character::HEIGHT / 2.0,
+            character::WIDTH,
+            character::HEIGHT,
+        )
+    }
+
+    // Walks the patrol and advances the walk or idle animation. `talking` is
+    // the loudness of the line the NPC is saying, if any; walking NPCs don't
+    // move their mouths.
+    pub fn update(
+        &mut self,
+        grid: &Grid,
+        talking: Option<f32>,
+        animation_speed: f32,
+        delta_time: f32,
+    ) {
+        if self.path.is_none() {
+            self.pause -= delta_time;
+            if self.pause <= 0.0 {
+                self.walk_to_next_waypoint(grid);
+            }
+        }
+
+        let walking = match &mut self.path {
+            Some(path) if !path.is_empty() => {
+                let target = grid.get_coord_from_grid(path[0].0, path[0].1);
+                let direction = (target - self.position).normalize_or_zero();
+                self.position += direction * self.data.speed * delta_time;
+                if direction != Vec2::ZERO {
+                    self.direction = Game::vec_to_direction(direction);
+                }
+                if (self.position - target).length_squared() < 25.0 {
+                    path.remove(0);
+                }
+                true
+            }
+            Some(_) => {
+                self.path = None;
+                self.pause = self.schedule_entry().pause;
+                false
+            }
+            None => false,
+        };
+
+        let state = match talking {
+            _ if walking => AnimationState::Walking,
+            Some(amplitude) => AnimationState::Talking(amplitude),
+            None => AnimationState::Idle,
+        };
+        // The loudness changes every frame without restarting the animation
+        if std::mem::discriminant(&state) != std::mem::discriminant(&self.animation_state) {
+            self.animation_index = 0;
+            self.animation_timer = 0.0;
+        }
+        self.animation_state = state;
+        let frames = match (&self.animation_state, &self.data.idle_frames) {
+            (AnimationState::Walking, _) => 8,
+            (_, Some(idle)) => idle.count.max(1),
+            _ => 1,
+        };
+        self.animation_timer += delta_time;
+        if self.animation_timer >= animation_speed {
+            self.animation_timer -= animation_speed;
+            self.animation_index = (self.animation_index + 1) % frames;
+        }
+    }
+
+    fn walk_to_next_waypoint(&mut self, grid: &Grid) {
+        let entry = self.schedule_entry();
+        if entry.patrol.is_empty() {
+            return;
+        }
+        let waypoint = (self.waypoint + 1) % (entry.patrol.len() + 1);
+        let target = match waypoint {
+            0 => Vec2::new(entry.x, entry.y),
+            n => Vec2::from(entry.patrol[n - 1]),
+        };
+        self.waypoint = waypoint;
+        let start = grid.get_grid_from_coord(self.position);
+        let goal = grid.get_grid_from_coord(target);
+        match grid.pathfind(start, goal) {
+            Ok(path) => self.path = Some(path),
+            Err(e) => {
+                log::debug!(target: crate::logging::GRID, "{} can't patrol: {}", self.data.name, e);
+                self.pause = npc::RETRY_DELAY;
+            }
+        }
+    }
+
+    // Whether a guard sees a position: inside the vision cone with no blocked
+    // node in between
+    pub fn can_see(&self, grid: &Grid, position: Vec2) -> bool {
+        let Some(guard) = &self.data.guard else {
+            return false;
+        };
+        guard.in_cone(self.position, self.direction.vector(), position)
+            && grid.has_line_of_sight(
+                grid.get_grid_from_coord(self.position),
+                grid.get_grid_from_coord(position),
+            )
+    }
+
+    // Fills the detection meter while any of the positions is seen and drains
+    // it otherwise. Returns true when the meter has filled up.
+    pub fn watch(&mut self, grid: &Grid, positions: &[Vec2], delta_time: f32) -> bool {
+        let Some(guard) = &self.data.guard else {
+            return false;
+        };
+        let rate = 1.0 / guard.detection_time.max(0.01);
+        if positions
+            .iter()
+            .any(|&position| self.can_see(grid, position))
+        {
+            self.detection = (self.detection + rate * delta_time).min(1.0);
+        } else {
+            self.detection = (self.detection - guard::DECAY_RATE * delta_time).max(0.0);
+        }
+        self.detection >= 1.0
+    }
+
+    // Frame to draw, and the standing frame to fall back to when it is missing
+    pub fn frame_paths(&self) -> (String, String) {
+        let direction = self.direction as u8;
+        let standing = self.data.frame_path(direction, 0, 0);
+        let path = match self.animation_state {
+            AnimationState::Walking => {
+                let cycle = if self.animation_index < 4 { 0 } else { 7 };
+                self.data
+                    .frame_path(direction, self.animation_index % 4, cycle)
+            }
+            AnimationState::Talking(amplitude) => match &self.data.talk_frames {
+                Some(talk) if talk.count > 0 => {
+                    let frame = ((amplitude * talk.count as f32) as usize).min(talk.count - 1);
+                    self.data.frame_path(direction, frame, talk.state)
+                }
+                _ => standing.clone(),
+            },
+            AnimationState::Idle => match &self.data.idle_frames {
+                Some(idle) => self
+                    .data
+                    .frame_path(direction, self.animation_index, idle.state),
+                None => standing.clone(),
+            },
+        };
+        (path, standing)
+    }
+}
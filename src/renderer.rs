@@ -1,6 +1,13 @@
 use crate::asset_manager::AssetManager;
-use crate::config::{character, dialog, inventory};
-use crate::{ClickableArea, Game, OverlayAsset, Scene};
+use crate::audio::{AudioCategory, Bus};
+use crate::config::{
+    character, dialog, guard, hotspots, hover, inventory, menu, sequence, subtitles,
+};
+use crate::grid::Surface;
+use crate::logging;
+use crate::npc::Npc;
+use crate::{AnimationState, CursorType, Game, InventoryData, OverlayAsset, Scene};
+use log::Level;
 use macroquad::prelude::*;
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
@@ -12,6 +19,7 @@ struct DrawableItem<'a> {
 
 enum DrawableType<'a> {
     Character(usize),
+    Npc(usize),
     OverlayAsset(&'a OverlayAsset),
 }
 
@@ -23,6 +31,13 @@ impl<'a> DrawableItem<'a> {
         }
     }
 
+    fn new_npc(index: usize, y: f32) -> Self {
+        DrawableItem {
+            y_position: ((y + character::HEIGHT) * 1000.0) as i32,
+            item: DrawableType::Npc(index),
+        }
+    }
+
     fn new_overlay(overlay: &'a OverlayAsset) -> Self {
         DrawableItem {
             y_position: ((overlay.y * 3.0 + overlay.height as f32) * 1000.0) as i32, // 3.0 is the scale factor
@@ -66,6 +81,14 @@ impl Renderer {
         }
     }
 
+    // Switches between fullscreen and a window of the given size
+    pub fn apply_display(&self, fullscreen: bool, resolution: Option<(u32, u32)>) {
+        set_fullscreen(fullscreen);
+        if !fullscreen && let Some((width, height)) = resolution {
+            request_new_screen_size(width as f32, height as f32);
+        }
+    }
+
     pub fn update_window_size(&mut self, window_size: Vec2) {
         self.window_size = window_size;
         self.game_rect = Self::calculate_game_rect(self.window_size);
@@ -113,25 +136,335 @@ impl Renderer {
     pub fn draw(&self, game: &Game, asset_manager: &AssetManager) {
         clear_background(BLACK);
 
+        if game.video_player.is_playing() {
+            self.draw_video(game, asset_manager);
+            return;
+        }
+
+        if game.menu.is_on_title() {
+            self.draw_menu(game, asset_manager);
+            self.draw_ui(game, asset_manager);
+            return;
+        }
+
         if let Some(current_scene) = game.get_current_scene() {
             self.draw_scene(game, current_scene, asset_manager);
         } else {
             self.draw_error_message("Scene not found");
         }
+        if game.reveal_hotspots {
+            self.draw_hotspots(game, asset_manager);
+        }
 
         self.draw_inventory(game, asset_manager);
         self.draw_dialog_menu(game, asset_manager);
+        self.draw_sequence(game, asset_manager);
+        self.draw_subtitles(game, asset_manager);
         self.draw_debug(game);
+        if game.menu.is_open() {
+            let (x, y) = self.get_scaled_pos(0.0, 0.0);
+            let scale = self.get_scale();
+            draw_rectangle(
+                x,
+                y,
+                1920.0 * scale,
+                1440.0 * scale,
+                menu::PAUSE_OVERLAY_COLOR,
+            );
+            self.draw_menu(game, asset_manager);
+        } else {
+            self.draw_hover_label(game, asset_manager);
+        }
         self.draw_ui(game, asset_manager);
     }
 
+    // The current video frame, fitted into the game area and centered so it is
+    // letterboxed the same way as the game
+    fn draw_video(&self, game: &Game, asset_manager: &AssetManager) {
+        let Some(texture) = game
+            .video_player
+            .shown_frame_path()
+            .and_then(|path| asset_manager.get_texture(&path))
+        else {
+            return;
+        };
+        let fit = (self.game_rect.w / texture.width()).min(self.game_rect.h / texture.height());
+        let size = texture.size() * fit;
+        draw_texture_ex(
+            texture,
+            self.game_rect.x + (self.game_rect.w - size.x) / 2.0,
+            self.game_rect.y + (self.game_rect.h - size.y) / 2.0,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(size),
+                ..Default::default()
+            },
+        );
+    }
+
+    // Images and the fade of the playing sequence, over everything but subtitles
+    fn draw_sequence(&self, game: &Game, asset_manager: &AssetManager) {
+        let scale = self.get_scale();
+        for overlay in &game.sequence_player.overlays {
+            let Some(texture) = asset_manager.get_texture(&overlay.texture) else {
+                continue;
+            };
+            let (position, size) = match overlay.position {
+                Some(position) => (position, texture.size()),
+                None => (Vec2::ZERO, Vec2::new(1920.0, 1440.0)),
+            };
+            let (x, y) = self.get_scaled_pos(position.x, position.y);
+            draw_texture_ex(
+                texture,
+                x,
+                y,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(size * scale),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let fade = game.sequence_player.fade_amount();
+        if fade > 0.0 {
+            let mut color = sequence::FADE_COLOR;
+            color.a *= fade;
+            let (x, y) = self.get_scaled_pos(0.0, 0.0);
+            draw_rectangle(x, y, 1920.0 * scale, 1440.0 * scale, color);
+        }
+    }
+
+    fn draw_subtitles(&self, game: &Game, asset_manager: &AssetManager) {
+        let Some(text) = game
+            .voice_queue
+            .current_line()
+            .and_then(|(line, _)| line.text.as_ref())
+        else {
+            return;
+        };
+        let scale = self.get_scale();
+        let font = asset_manager.get_font("dialog");
+        let font_size = (subtitles::FONT_SIZE * scale) as u16;
+        let padding = subtitles::PADDING * scale;
+
+        // Wrap on words to fit the width
+        let mut lines: Vec<String> = Vec::new();
+        for word in text.split_whitespace() {
+            let candidate = match lines.last() {
+                Some(line) => format!("{} {}", line, word),
+                None => word.to_string(),
+            };
+            let fits = measure_text(&candidate, font, font_size, 1.0).width
+                <= subtitles::MAX_WIDTH * scale;
+            match lines.last_mut() {
+                Some(line) if fits => *line = candidate,
+                _ => lines.push(word.to_string()),
+            }
+        }
+
+        let first_y = subtitles::BOTTOM_Y - (lines.len() as f32 - 1.0) * subtitles::LINE_SPACING;
+        for (i, line) in lines.iter().enumerate() {
+            let text_size = measure_text(line, font, font_size, 1.0);
+            let (center_x, y) =
+                self.get_scaled_pos(1920.0 / 2.0, first_y + i as f32 * subtitles::LINE_SPACING);
+            let x = center_x - text_size.width / 2.0;
+            draw_rectangle(
+                x - padding,
+                y - text_size.offset_y - padding,
+                text_size.width + padding * 2.0,
+                text_size.height + padding * 2.0,
+                subtitles::BACKGROUND_COLOR,
+            );
+            draw_text_ex(
+                line,
+                x,
+                y,
+                TextParams {
+                    font,
+                    font_size,
+                    color: subtitles::TEXT_COLOR,
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    fn draw_hover_label(&self, game: &Game, asset_manager: &AssetManager) {
+        let Some(label) = &game.hover_label else {
+            return;
+        };
+        let scale = self.get_scale();
+        let font = asset_manager.get_font("dialog");
+        let font_size = (hover::FONT_SIZE * scale) as u16;
+        let padding = hover::PADDING * scale;
+        let text_size = measure_text(label, font, font_size, 1.0);
+
+        // Next to the cursor, flipped to its left side near the right edge
+        let mut x = game.cursor.position.x + hover::OFFSET_X;
+        if x + (text_size.width + padding) / scale > 1920.0 {
+            x = game.cursor.position.x - hover::OFFSET_X - (text_size.width + padding) / scale;
+        }
+        let (x, y) = self.get_scaled_pos(x, game.cursor.position.y + hover::OFFSET_Y);
+
+        draw_rectangle(
+            x - padding,
+            y - text_size.offset_y - padding,
+            text_size.width + padding * 2.0,
+            text_size.height + padding * 2.0,
+            hover::BACKGROUND_COLOR,
+        );
+        draw_text_ex(
+            label,
+            x,
+            y,
+            TextParams {
+                font,
+                font_size,
+                color: hover::TEXT_COLOR,
+                ..Default::default()
+            },
+        );
+    }
+
+    fn draw_hotspots(&self, game: &Game, asset_manager: &AssetManager) {
+        let scale = self.get_scale();
+        let font = asset_manager.get_font("dialog");
+        let font_size = (hotspots::FONT_SIZE * scale) as u16;
+        let padding = hotspots::LABEL_PADDING * scale;
+
+        for hotspot in game.hotspots() {
+            let color = if hotspot.in_range {
+                hotspots::COLOR
+            } else {
+                hotspots::OUT_OF_RANGE_COLOR
+            };
+            let (x, y) = self.get_scaled_pos(hotspot.rect.x, hotspot.rect.y);
+            let (width, height) = (hotspot.rect.w * scale, hotspot.rect.h * scale);
+            draw_rectangle_lines(
+                x,
+                y,
+                width,
+                height,
+                hotspots::OUTLINE_THICKNESS * scale,
+                color,
+            );
+            draw_circle(
+                x + width / 2.0,
+                y + height / 2.0,
+                hotspots::MARKER_RADIUS * scale,
+                color,
+            );
+
+            if hotspot.label.is_empty() {
+                continue;
+            }
+            // Label centered above the hotspot
+            let text_size = measure_text(&hotspot.label, font, font_size, 1.0);
+            let label_x = x + (width - text_size.width) / 2.0;
+            let label_y = y - padding;
+            draw_rectangle(
+                label_x - padding,
+                label_y - text_size.offset_y - padding,
+                text_size.width + padding * 2.0,
+                text_size.height + padding * 2.0,
+                hotspots::LABEL_BACKGROUND_COLOR,
+            );
+            draw_text_ex(
+                &hotspot.label,
+                label_x,
+                label_y,
+                TextParams {
+                    font,
+                    font_size,
+                    color,
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    fn draw_menu(&self, game: &Game, asset_manager: &AssetManager) {
+        let Some(screen) = game.menu.current() else {
+            return;
+        };
+        let scale = self.get_scale();
+
+        // Backgrounds, decorations and textured buttons for this screen
+        for item in game
+            .ui
+            .menu_items
+            .iter()
+            .filter(|i| i.screen == Some(screen))
+        {
+            if let Some(texture) = asset_manager.get_texture(&item.texture) {
+                let (x, y) = self.get_scaled_pos(item.position[0], item.position[1]);
+                draw_texture_ex(
+                    texture,
+                    x,
+                    y,
+                    WHITE,
+                    DrawTextureParams {
+                        dest_size: Some(Vec2::new(item.size[0] * scale, item.size[1] * scale)),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+
+        let buttons = game.menu.buttons(
+            &game.ui.menu_items,
+            &game.audio_system,
+            &game.strings,
+            game.settings.fullscreen,
+        );
+        let font = asset_manager.get_font("dialog");
+        for (i, button) in buttons.iter().enumerate() {
+            let selected = i == game.menu.selected;
+            let (x, y) = self.get_scaled_pos(button.rect.x, button.rect.y);
+            let (width, height) = (button.rect.w * scale, button.rect.h * scale);
+
+            if button.texture.is_some() {
+                if !button.enabled {
+                    draw_rectangle(x, y, width, height, menu::PAUSE_OVERLAY_COLOR);
+                }
+                if selected {
+                    draw_rectangle_lines(x, y, width, height, 4.0, menu::SELECTED_TEXT_COLOR);
+                }
+                continue;
+            }
+
+            let color = if !button.enabled {
+                menu::DISABLED_TEXT_COLOR
+            } else if selected {
+                menu::SELECTED_TEXT_COLOR
+            } else {
+                menu::TEXT_COLOR
+            };
+            draw_rectangle(x, y, width, height, menu::BUTTON_COLOR);
+            let font_size = menu::FONT_SIZE * scale;
+            let text_size = measure_text(&button.label, font, font_size as u16, 1.0);
+            draw_text_ex(
+                &button.label,
+                x + (width - text_size.width) / 2.0,
+                y + height / 2.0 + font_size / 3.0,
+                TextParams {
+                    font,
+                    font_size: font_size as u16,
+                    color,
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
     fn draw_scene(&self, game: &Game, scene: &Scene, asset_manager: &AssetManager) {
         let Some(texture) = asset_manager.get_texture(&scene.background) else {
             self.draw_loading_message(&scene.background);
             return;
         };
 
-        self.draw_background(&texture);
+        self.draw_background(texture);
         self.draw_world_items(game, asset_manager);
         let scale = self.get_scale();
 
@@ -141,6 +474,9 @@ impl Renderer {
         for (i, pos) in game.characters.positions.iter().enumerate() {
             heap.push(DrawableItem::new_character(i, pos.y));
         }
+        for (i, npc) in game.npcs.iter().enumerate() {
+            heap.push(DrawableItem::new_npc(i, npc.position.y));
+        }
 
         for overlay in &scene.overlay_assets {
             match overlay.z_value {
@@ -162,6 +498,9 @@ impl Renderer {
                         asset_manager,
                     );
                 }
+                DrawableType::Npc(index) => {
+                    self.draw_npc(&game.npcs[index], scale, asset_manager);
+                }
                 DrawableType::OverlayAsset(overlay) => {
                     self.draw_overlay_asset(overlay, asset_manager);
                 }
@@ -205,34 +544,100 @@ impl Renderer {
             game.characters.positions[index].y,
         );
 
+        let data = &game.characters.data[index];
+        let direction = game.characters.directions[index] as u8;
         let cycle = if game.characters.animation_indices[index] < 4 {
             0
         } else {
             7
         };
         let frame = game.characters.animation_indices[index] % 4;
+        let mut texture_path = data.frame_path(direction, frame, cycle);
+        let mut stretch = 1.0;
+
+        if let AnimationState::Talking(amplitude) = game.characters.animation_states[index] {
+            // Use the mouth frames when the character has them, otherwise
+            // stretch the standing frame a little in time with the voice
+            let talk_frames = data.talk_frames.as_ref().filter(|talk| talk.count > 0);
+            let talk_path = talk_frames.map(|talk| {
+                let frame = ((amplitude * talk.count as f32) as usize).min(talk.count - 1);
+                data.frame_path(direction, frame, talk.state)
+            });
+            match talk_path.filter(|path| asset_manager.get_texture(path).is_some()) {
+                Some(path) => texture_path = path,
+                None => stretch += amplitude * character::TALK_STRETCH,
+            }
+        }
 
-        let filename = format!(
-            "{}{}{}{}.png",
-            game.characters.data[index].name, game.characters.directions[index] as u8, frame, cycle
+        self.draw_sprite(
+            asset_manager.get_texture(&texture_path),
+            game.characters.positions[index],
+            stretch,
+            scale,
         );
-        let texture_path = format!("Huvudmeny/Gubbar/{}", filename);
 
-        if let Some(texture) = asset_manager.get_texture(&texture_path) {
-            let xt = texture.width() / 2.0 * scale;
-            let yt = texture.height() / 2.0 * scale;
+        if is_active {
+            let indicator_size = 10.0 * scale;
+            draw_circle(
+                x + x_offset,
+                y + y_offset - 40.0 * scale,
+                indicator_size,
+                GREEN,
+            );
+        }
+    }
+
+    fn draw_npc(&self, npc: &Npc, scale: f32, asset_manager: &AssetManager) {
+        let (path, standing) = npc.frame_paths();
+        let texture = asset_manager
+            .get_texture(&path)
+            .or_else(|| asset_manager.get_texture(&standing));
+        self.draw_sprite(texture, npc.position, 1.0, scale);
+        if npc.detection > 0.0 {
+            self.draw_detection_meter(npc, scale);
+        }
+    }
+
+    // How close a guard is to spotting the gang, above their head
+    fn draw_detection_meter(&self, npc: &Npc, scale: f32) {
+        let top = npc.rect().y - 20.0;
+        let (x, y) = self.get_scaled_pos(npc.position.x - guard::METER_WIDTH / 2.0, top);
+        let (width, height) = (guard::METER_WIDTH * scale, guard::METER_HEIGHT * scale);
+        let color = if npc.detection >= 1.0 {
+            guard::METER_FULL_COLOR
+        } else {
+            guard::METER_COLOR
+        };
+        draw_rectangle(x, y, width, height, guard::METER_BACKGROUND);
+        draw_rectangle(x, y, width * npc.detection, height, color);
+    }
+
+    // A character frame standing at a grid position, or a red square when the
+    // frame is missing
+    fn draw_sprite(&self, texture: Option<&Texture2D>, position: Vec2, stretch: f32, scale: f32) {
+        // In order for characters to line up on the grid
+        // we need to offset them up.
+        let x_offset = character::X_OFFSET * scale;
+        let y_offset = character::Y_OFFSET * scale;
+        let (x, y) = self.get_scaled_pos(position.x, position.y);
+
+        if let Some(texture) = texture {
+            let width = texture.width() * scale;
+            let height = texture.height() * scale;
+            let stretched_height = height * stretch;
+            // Keep the feet in place when stretching
             draw_texture_ex(
-                &texture,
-                (x + x_offset) - xt,
-                (y + y_offset) - yt,
+                texture,
+                (x + x_offset) - width / 2.0,
+                (y + y_offset) + height / 2.0 - stretched_height,
                 WHITE,
                 DrawTextureParams {
-                    dest_size: Some(Vec2::new(texture.width() * scale, texture.height() * scale)),
+                    dest_size: Some(Vec2::new(width, stretched_height)),
                     ..Default::default()
                 },
             );
         } else {
-            println!("Texture not found for filename: {}", filename);
+            // Missing frames are listed by the startup asset audit
             let rect_size = 50.0 * scale;
             draw_rectangle(
                 (x + x_offset) - rect_size / 2.0,
@@ -242,16 +647,6 @@ impl Renderer {
                 RED,
             );
         }
-
-        if is_active {
-            let indicator_size = 10.0 * scale;
-            draw_circle(
-                x + x_offset,
-                y + y_offset - 40.0 * scale,
-                indicator_size,
-                GREEN,
-            );
-        }
     }
 
     fn draw_overlay_asset(&self, overlay: &OverlayAsset, asset_manager: &AssetManager) {
@@ -260,7 +655,7 @@ impl Renderer {
             let (x, y) = self.get_scaled_pos(ox, oy);
             let scale = self.get_scale();
             draw_texture_ex(
-                &texture,
+                texture,
                 x,
                 y,
                 WHITE,
@@ -269,15 +664,12 @@ impl Renderer {
                     ..Default::default()
                 },
             );
-        } else {
-            println!("Overlay texture not found: {}", overlay.texture_path);
         }
     }
 
     fn draw_world_items(&self, game: &Game, asset_manager: &AssetManager) {
         let current_scene_items = &game.world_items[game.current_scene as usize];
-        let mouse_pos = Vec2::from(mouse_position());
-        let game_pos = self.get_game_coordinates(mouse_pos);
+        let game_pos = game.cursor.position;
 
         for item_instance in current_scene_items {
             let item = game
@@ -295,7 +687,7 @@ impl Renderer {
                 let (x, y) = self.get_scaled_pos(item_instance.x, item_instance.y);
                 let scale = self.get_scale();
                 draw_texture_ex(
-                    &texture,
+                    texture,
                     x,
                     y,
                     WHITE,
@@ -312,10 +704,8 @@ impl Renderer {
     }
 
     fn draw_inventory(&self, game: &Game, asset_manager: &AssetManager) {
-        let button_texture_path = format!(
-            "Huvudmeny/inventory/väska{}.png",
-            game.inventory.animation_frame + 1
-        );
+        let button_texture_path =
+            InventoryData::button_texture_path(game.inventory.animation_frame);
         if let Some(texture) = asset_manager.get_texture(&button_texture_path) {
             let scale = self.get_scale();
 
@@ -331,7 +721,7 @@ impl Renderer {
 
             // Draw the background texture
             draw_texture_ex(
-                &texture,
+                texture,
                 screen_x,
                 screen_y,
                 WHITE,
@@ -346,12 +736,11 @@ impl Renderer {
                 let slots_start_x = game_x + inventory::START_X;
 
                 for i in 0..inventory::SLOT_COUNT {
-                    let item_index = i + game.inventory.scroll_offset;
-                    let slot = if item_index < inventory::INVENTORY_SIZE {
-                        game.inventory.items[item_index]
-                    } else {
-                        None
-                    };
+                    // The dragged item leaves its slot empty
+                    let slot = game.inventory.item_in_slot(i).filter(|_| {
+                        game.inventory.drag.map(|drag| drag.index)
+                            != Some(game.inventory.scroll_offset + i)
+                    });
 
                     let slot_x =
                         slots_start_x + (inventory::SLOT_SIZE + inventory::SLOT_SPACING) * i as f32;
@@ -359,9 +748,8 @@ impl Renderer {
                     let (screen_x, screen_y) = self.get_scaled_pos(slot_x, slot_y);
                     let scaled_slot_size = inventory::SLOT_SIZE * scale;
 
-                    let slot_visible_width = (visible_width - (slot_x - game_x))
-                        .max(0.0)
-                        .min(inventory::SLOT_SIZE);
+                    let slot_visible_width =
+                        (visible_width - (slot_x - game_x)).clamp(0.0, inventory::SLOT_SIZE);
 
                     if slot_visible_width > 0.0 {
                         let slot_color = if Some(i) == game.inventory.hovered_slot {
@@ -379,79 +767,70 @@ impl Renderer {
                             slot_color,
                         );
 
+                        // A freshly combined item glows and fades out
+                        if game.inventory.combined == Some(game.inventory.scroll_offset + i) {
+                            let mut color = inventory::COMBINE_COLOR;
+                            color.a *= game.inventory.combine_timer / inventory::COMBINE_FLASH_TIME;
+                            draw_rectangle(
+                                screen_x,
+                                screen_y,
+                                scaled_slot_size * (slot_visible_width / inventory::SLOT_SIZE),
+                                scaled_slot_size,
+                                color,
+                            );
+                        }
+
                         // Draw item in slot if it exists
-                        if let Some(item_id) = slot {
-                            if let Some(item) = game.items.iter().find(|i| i.id == item_id) {
-                                if let Some(mut item_texture) =
-                                    asset_manager.get_texture(&item.textures.in_inventory)
-                                {
-                                    if let Some(item_texture_text) =
-                                        asset_manager.get_texture(&item.textures.in_inventory_text)
-                                    {
-                                        item_texture = if Some(i) == game.inventory.hovered_slot {
-                                            item_texture_text
-                                        } else {
-                                            item_texture
-                                        };
-                                    }
-
-                                    // text asset is wider than the item asset
-                                    // TODO: clean up as this does nothing when
-                                    // item_texture is changed above
-                                    // still works though
-                                    let max_width = item_texture.width().max(
-                                        asset_manager
-                                            .get_texture(&item.textures.in_inventory_text)
-                                            .map_or(0.0, |t| t.width()),
-                                    );
-                                    let max_height = item_texture.height();
-
-                                    // Calculate scaling factors
-                                    let scale_x = inventory::SLOT_SIZE / max_width;
-                                    let scale_y = inventory::SLOT_SIZE / max_height;
-                                    let item_scale = scale_x.min(scale_y);
-
-                                    let scaled_item_width =
-                                        item_texture.width() * item_scale * scale;
-                                    let scaled_item_height =
-                                        item_texture.height() * item_scale * scale;
-
-                                    // Center the item in the slot
-                                    let item_x =
-                                        screen_x + (scaled_slot_size - scaled_item_width) / 2.0;
-                                    let item_y =
-                                        screen_y + (scaled_slot_size - scaled_item_height) / 2.0;
-
-                                    // Calculate the visible portion of the item
-                                    let visible_item_width = (slot_visible_width
-                                        / inventory::SLOT_SIZE
-                                        * scaled_item_width)
-                                        .min(scaled_item_width);
-
-                                    // Adjust the source rectangle to account for the item's original dimensions
-                                    let source_rect = Rect::new(
-                                        0.0,
-                                        0.0,
-                                        visible_item_width / (item_scale * scale),
-                                        item_texture.height(),
-                                    );
-
-                                    draw_texture_ex(
-                                        item_texture,
-                                        item_x,
-                                        item_y,
-                                        WHITE,
-                                        DrawTextureParams {
-                                            dest_size: Some(Vec2::new(
-                                                visible_item_width,
-                                                scaled_item_height,
-                                            )),
-                                            source: Some(source_rect),
-                                            ..Default::default()
-                                        },
-                                    );
-                                }
-                            }
+                        if let Some(item_id) = slot
+                            && let Some(item) = game.items.iter().find(|i| i.id == item_id)
+                            && let Some(item_texture) =
+                                asset_manager.get_texture(&item.textures.in_inventory)
+                        {
+                            // Scaled to fit the wider name texture, which keeps
+                            // the items at the size they have in the original game
+                            let max_width = item_texture.width().max(
+                                asset_manager
+                                    .get_texture(&item.textures.in_inventory_text)
+                                    .map_or(0.0, |t| t.width()),
+                            );
+                            let scale_x = inventory::SLOT_SIZE / max_width;
+                            let scale_y = inventory::SLOT_SIZE / item_texture.height();
+                            let item_scale = scale_x.min(scale_y);
+
+                            let scaled_item_width = item_texture.width() * item_scale * scale;
+                            let scaled_item_height = item_texture.height() * item_scale * scale;
+
+                            // Center the item in the slot
+                            let item_x = screen_x + (scaled_slot_size - scaled_item_width) / 2.0;
+                            let item_y = screen_y + (scaled_slot_size - scaled_item_height) / 2.0;
+
+                            // Calculate the visible portion of the item
+                            let visible_item_width = (slot_visible_width / inventory::SLOT_SIZE
+                                * scaled_item_width)
+                                .min(scaled_item_width);
+
+                            // Adjust the source rectangle to account for the item's original dimensions
+                            let source_rect = Rect::new(
+                                0.0,
+                                0.0,
+                                visible_item_width / (item_scale * scale),
+                                item_texture.height(),
+                            );
+
+                            draw_texture_ex(
+                                item_texture,
+                                item_x,
+                                item_y,
+                                WHITE,
+                                DrawTextureParams {
+                                    dest_size: Some(Vec2::new(
+                                        visible_item_width,
+                                        scaled_item_height,
+                                    )),
+                                    source: Some(source_rect),
+                                    ..Default::default()
+                                },
+                            );
                         }
                     }
                 }
@@ -460,14 +839,118 @@ impl Renderer {
                     // Draw arrow buttons
                     self.draw_inventory_arrow(game, asset_manager, true); // Left arrow
                     self.draw_inventory_arrow(game, asset_manager, false); // Right arrow
+                    self.draw_inventory_panel(game, asset_manager, slots_start_x);
                 }
             }
-        } else {
-            println!(
-                "Inventory button texture not found: {}",
-                button_texture_path
+        }
+        self.draw_dragged_item(game, asset_manager);
+    }
+
+    // The hovered item's name texture in a panel above its slot, or its name
+    // when the item has no such texture
+    fn draw_inventory_panel(&self, game: &Game, asset_manager: &AssetManager, slots_x: f32) {
+        if game.inventory.drag.is_some() {
+            return;
+        }
+        let Some(slot) = game.inventory.hovered_slot else {
+            return;
+        };
+        let Some(item) = game
+            .inventory
+            .item_in_slot(slot)
+            .and_then(|item_id| game.items.iter().find(|item| item.id == item_id))
+        else {
+            return;
+        };
+        let scale = self.get_scale();
+        let padding = inventory::PANEL_PADDING;
+        let slot_center_x = slots_x
+            + (inventory::SLOT_SIZE + inventory::SLOT_SPACING) * slot as f32
+            + inventory::SLOT_SIZE / 2.0;
+        let bottom = inventory::TOP_Y - inventory::PANEL_MARGIN;
+
+        if let Some(texture) = asset_manager.get_texture(&item.textures.in_inventory_text) {
+            let (width, height) = (texture.width(), texture.height());
+            let x = (slot_center_x - width / 2.0).clamp(padding, 1920.0 - width - padding);
+            let y = bottom - height - padding;
+            let (screen_x, screen_y) = self.get_scaled_pos(x - padding, y - padding);
+            draw_rectangle(
+                screen_x,
+                screen_y,
+                (width + padding * 2.0) * scale,
+                (height + padding * 2.0) * scale,
+                inventory::PANEL_COLOR,
+            );
+            let (screen_x, screen_y) = self.get_scaled_pos(x, y);
+            draw_texture_ex(
+                texture,
+                screen_x,
+                screen_y,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(Vec2::new(width * scale, height * scale)),
+                    ..Default::default()
+                },
             );
+            return;
         }
+
+        let font = asset_manager.get_font("dialog");
+        let font_size = (inventory::PANEL_FONT_SIZE * scale) as u16;
+        let name = game.strings.get(&item.name);
+        let text_size = measure_text(name, font, font_size, 1.0);
+        let (center_x, baseline) = self.get_scaled_pos(slot_center_x, bottom - padding);
+        let x = center_x - text_size.width / 2.0;
+        let y = baseline - (text_size.height - text_size.offset_y);
+        draw_rectangle(
+            x - padding * scale,
+            y - text_size.offset_y - padding * scale,
+            text_size.width + padding * 2.0 * scale,
+            text_size.height + padding * 2.0 * scale,
+            inventory::PANEL_COLOR,
+        );
+        draw_text_ex(
+            name,
+            x,
+            y,
+            TextParams {
+                font,
+                font_size,
+                color: inventory::PANEL_TEXT_COLOR,
+                ..Default::default()
+            },
+        );
+    }
+
+    // The item being dragged follows the cursor
+    fn draw_dragged_item(&self, game: &Game, asset_manager: &AssetManager) {
+        let Some(texture) = game
+            .inventory
+            .drag
+            .and_then(|drag| game.inventory.shown().get(drag.index))
+            .and_then(|&item_id| game.items.iter().find(|item| item.id == item_id))
+            .and_then(|item| asset_manager.get_texture(&item.textures.in_inventory))
+        else {
+            return;
+        };
+        let scale = self.get_scale();
+        let item_scale =
+            (inventory::SLOT_SIZE / texture.width()).min(inventory::SLOT_SIZE / texture.height());
+        let (width, height) = (texture.width() * item_scale, texture.height() * item_scale);
+        let (x, y) = self.get_scaled_pos(
+            game.cursor.position.x - width / 2.0,
+            game.cursor.position.y - height / 2.0,
+        );
+        draw_texture_ex(
+            texture,
+            x,
+            y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(Vec2::new(width * scale, height * scale)),
+                ..Default::default()
+            },
+        );
     }
 
     fn draw_inventory_arrow(&self, game: &Game, asset_manager: &AssetManager, is_left: bool) {
@@ -478,9 +961,9 @@ impl Renderer {
         };
 
         let texture_path = if is_left {
-            "Huvudmeny/inventory/pilv-271.png"
+            inventory::LEFT_ARROW_TEXTURE
         } else {
-            "Huvudmeny/inventory/pilh-272.png"
+            inventory::RIGHT_ARROW_TEXTURE
         };
 
         if let Some(texture) = asset_manager.get_texture(texture_path) {
@@ -519,13 +1002,6 @@ impl Renderer {
         }
     }
 
-    fn draw_clickable_area(&self, area: &ClickableArea) {
-        let (x, y) = self.get_scaled_pos(area.x, area.y);
-        let width = area.width * self.get_scale();
-        let height = area.height * self.get_scale();
-        draw_rectangle_lines(x, y, width, height, 2.0, RED);
-    }
-
     fn draw_scene_description(&self, scene: &Scene) {
         let (desc_x, desc_y) = self.get_scaled_pos(20.0, 20.0);
         draw_text(
@@ -548,18 +1024,21 @@ impl Renderer {
         );
     }
 
-    fn draw_error_message(&self, message: &str) {
-        let (text_x, text_y) = self.get_scaled_pos(20.0, 20.0);
-        draw_text(message, text_x, text_y, 30.0 * self.get_scale(), RED);
+    pub fn draw_error_message(&self, message: &str) {
+        let font_size = 30.0 * self.get_scale();
+        for (i, line) in message.lines().enumerate() {
+            let (text_x, text_y) = self.get_scaled_pos(20.0, 40.0 + 36.0 * i as f32);
+            draw_text(line, text_x, text_y, font_size, RED);
+        }
     }
 
     fn draw_ui(&self, game: &Game, asset_manager: &AssetManager) {
-        for menu_item in &game.ui.menu_items {
+        for menu_item in game.ui.menu_items.iter().filter(|i| i.screen.is_none()) {
             if let Some(texture) = asset_manager.get_texture(&menu_item.texture) {
                 let (x, y) = self.get_scaled_pos(menu_item.position[0], menu_item.position[1]);
                 let scale = self.get_scale();
                 draw_texture_ex(
-                    &texture,
+                    texture,
                     x,
                     y,
                     WHITE,
@@ -574,37 +1053,36 @@ impl Renderer {
             }
         }
 
-        if let Some(cursor_texture) = asset_manager.get_texture(
-            &game
-                .ui
-                .cursors
-                .iter()
-                .find(|c| c.cursor_type == game.current_cursor)
-                .map(|c| &c.texture)
-                .unwrap_or(&String::new()),
-        ) {
-            let cursor_pos = mouse_position();
-            if let Some(cursor) = game
-                .ui
-                .cursors
-                .iter()
-                .find(|c| c.cursor_type == game.current_cursor)
-            {
-                let scale = self.get_scale();
-                draw_texture_ex(
-                    &cursor_texture,
-                    cursor_pos.0 - (cursor.hotspot[0] as f32 * scale),
-                    cursor_pos.1 - (cursor.hotspot[1] as f32 * scale),
-                    WHITE,
-                    DrawTextureParams {
-                        dest_size: Some(Vec2::new(
-                            cursor_texture.width() * scale,
-                            cursor_texture.height() * scale,
-                        )),
-                        ..Default::default()
-                    },
-                );
-            }
+        // Data without a cursor for the current type falls back to the normal one
+        let cursor = game
+            .ui
+            .cursors
+            .iter()
+            .find(|c| c.cursor_type == game.current_cursor)
+            .or_else(|| {
+                game.ui
+                    .cursors
+                    .iter()
+                    .find(|c| c.cursor_type == CursorType::Normal)
+            });
+        if let Some(cursor) = cursor
+            && let Some(cursor_texture) = asset_manager.get_texture(&cursor.texture)
+        {
+            let cursor_pos = self.get_scaled_pos(game.cursor.position.x, game.cursor.position.y);
+            let scale = self.get_scale();
+            draw_texture_ex(
+                cursor_texture,
+                cursor_pos.0 - (cursor.hotspot[0] as f32 * scale),
+                cursor_pos.1 - (cursor.hotspot[1] as f32 * scale),
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(Vec2::new(
+                        cursor_texture.width() * scale,
+                        cursor_texture.height() * scale,
+                    )),
+                    ..Default::default()
+                },
+            );
         }
     }
 
@@ -618,6 +1096,83 @@ impl Renderer {
             }
             self.draw_debug_info(game);
             self.draw_dialog_boxes(game);
+            self.draw_vision_cones(game);
+            if game.debug_tools.show_missing_assets {
+                self.draw_missing_assets(game);
+            }
+            if game.debug_tools.show_log {
+                self.draw_log_panel(game);
+            }
+        }
+    }
+
+    fn draw_log_panel(&self, game: &Game) {
+        const LINES: usize = 20;
+        let scale = self.get_scale();
+        let font_size = 20.0 * scale;
+        let line_height = 24.0;
+        let (x, top) = (20.0, 1440.0 - 40.0 - line_height * (LINES + 1) as f32);
+
+        let (panel_x, panel_y) = self.get_scaled_pos(x - 10.0, top - line_height);
+        draw_rectangle(
+            panel_x,
+            panel_y,
+            1500.0 * scale,
+            line_height * (LINES + 2) as f32 * scale,
+            Color::new(0.0, 0.0, 0.0, 0.7),
+        );
+
+        // Target levels, the selected target is adjusted with Tab and +/-
+        let mut target_x = x;
+        for (i, target) in logging::TARGETS.iter().enumerate() {
+            let text = format!("{}={}", target, logging::level(target));
+            let color = if i == game.debug_tools.log_target {
+                YELLOW
+            } else {
+                GRAY
+            };
+            let (text_x, text_y) = self.get_scaled_pos(target_x, top);
+            draw_text(&text, text_x, text_y, font_size, color);
+            target_x += 200.0;
+        }
+
+        for (i, entry) in logging::recent(LINES).iter().enumerate() {
+            let color = match entry.level {
+                Level::Error => RED,
+                Level::Warn => ORANGE,
+                Level::Info => WHITE,
+                Level::Debug => LIGHTGRAY,
+                Level::Trace => GRAY,
+            };
+            let text = format!("[{} {}] {}", entry.level, entry.target, entry.message);
+            let (text_x, text_y) = self.get_scaled_pos(x, top + line_height * (i + 1) as f32);
+            draw_text(&text, text_x, text_y, font_size, color);
+        }
+    }
+
+    fn draw_missing_assets(&self, game: &Game) {
+        let Some(scene) = game.get_current_scene() else {
+            return;
+        };
+        let missing = game.asset_audit.scene_missing(game.current_level, scene.id);
+        let font_size = 20.0 * self.get_scale();
+        let line_height = 24.0;
+        let x = 1100.0;
+        let mut y = 40.0;
+
+        let header = format!(
+            "Missing assets: {} in scene, {} total (see {})",
+            missing.len(),
+            game.asset_audit.missing_count(),
+            crate::config::paths::MISSING_ASSETS_REPORT
+        );
+        let (text_x, text_y) = self.get_scaled_pos(x, y);
+        draw_text(&header, text_x, text_y, font_size, YELLOW);
+
+        for path in missing {
+            y += line_height;
+            let (text_x, text_y) = self.get_scaled_pos(x, y);
+            draw_text(path, text_x, text_y, font_size, WHITE);
         }
     }
 
@@ -654,7 +1209,12 @@ impl Renderer {
                 "Animation Speed: {:.2}",
                 game.characters.animation_speeds[0]
             );
-            for (i, text) in [pos, anim_speed].iter().enumerate() {
+            let mixer = self.mixer_summary(game);
+            let speaker = format!(
+                "Speaking: {}",
+                game.voice_queue.current_speaker().unwrap_or("-")
+            );
+            for (i, text) in [pos, anim_speed, mixer, speaker].iter().enumerate() {
                 let (x, y) = self.get_scaled_pos(20.0, 90.0 + 30.0 * i as f32);
                 draw_text(text, x, y, 20.0 * self.get_scale(), WHITE);
             }
@@ -667,6 +1227,28 @@ impl Renderer {
         self.draw_scene_transitions(game);
     }
 
+    fn mixer_summary(&self, game: &Game) -> String {
+        let buses = [
+            ("master", Bus::Master),
+            ("music", Bus::Category(AudioCategory::Music)),
+            ("ambience", Bus::Category(AudioCategory::Ambience)),
+            ("dialog", Bus::Category(AudioCategory::Dialog)),
+            ("sfx", Bus::Category(AudioCategory::SoundEffect)),
+        ];
+        let parts: Vec<String> = buses
+            .iter()
+            .map(|&(name, bus)| {
+                let muted = if game.audio_system.is_muted(bus) {
+                    " (muted)"
+                } else {
+                    ""
+                };
+                format!("{} {:.1}{}", name, game.audio_system.get_volume(bus), muted)
+            })
+            .collect();
+        format!("Mixer: {}", parts.join(", "))
+    }
+
     fn draw_scene_transitions(&self, game: &Game) {
         if let Some(current_scene) = game.get_current_scene() {
             for transition in &current_scene.scene_transitions {
@@ -695,6 +1277,36 @@ impl Renderer {
         }
     }
 
+    // Vision cones of the guards, with a line to each character they see
+    fn draw_vision_cones(&self, game: &Game) {
+        const SEGMENTS: usize = 8;
+        for npc in &game.npcs {
+            let Some(data) = &npc.data.guard else {
+                continue;
+            };
+            let (left, right) = data.cone_edges(npc.direction.vector());
+            let eye = self.get_scaled_pos(npc.position.x, npc.position.y).into();
+            let step = left.angle_between(right) / SEGMENTS as f32;
+            for i in 0..SEGMENTS {
+                let a = npc.position + Vec2::from_angle(step * i as f32).rotate(left);
+                let b = npc.position + Vec2::from_angle(step * (i + 1) as f32).rotate(left);
+                let a = self.get_scaled_pos(a.x, a.y).into();
+                let b = self.get_scaled_pos(b.x, b.y).into();
+                draw_triangle(eye, a, b, guard::CONE_COLOR);
+            }
+
+            for &position in &game.characters.positions[..game.characters.count] {
+                if npc.can_see(&game.grid, position) {
+                    let (x, y) = self.get_scaled_pos(position.x, position.y);
+                    draw_line(eye.x, eye.y, x, y, 2.0, RED);
+                }
+            }
+            let (x, y) = self.get_scaled_pos(npc.position.x, npc.rect().y - 30.0);
+            let text = format!("{:.0}%", npc.detection * 100.0);
+            draw_text(&text, x, y, 20.0 * self.get_scale(), YELLOW);
+        }
+    }
+
     fn draw_debug_grid(&self, game: &Game) {
         let grid_color = Color::new(0.0, 1.0, 0.0, 0.5);
         let scale = self.get_scale();
@@ -735,6 +1347,17 @@ impl Renderer {
                     draw_circle(draw_x, draw_y, circle_radius, BLACK);
                 }
 
+                // Surface tags used for footsteps
+                if let Some(surface) = game.grid.surface_at((x, y)) {
+                    let color = match surface {
+                        Surface::Wood => BROWN,
+                        Surface::Stone => GRAY,
+                        Surface::Carpet => PURPLE,
+                    };
+                    let size = 6.0 * scale;
+                    draw_rectangle(draw_x - size / 2.0, draw_y + size, size, size, color);
+                }
+
                 let color = if game.grid.is_node_walkable((x, y)) {
                     WHITE
                 } else {
@@ -770,85 +1393,80 @@ impl Renderer {
     }
 
     fn draw_dialog_menu(&self, game: &Game, asset_manager: &AssetManager) {
-        if game.dialog_menu.open {
-            if let Some(dialog_background) =
+        if game.dialog_menu.open
+            && let Some(dialog_background) =
                 asset_manager.get_texture(&game.ui.general_textures.dialog_background)
-            {
-                let scale = self.get_scale();
+        {
+            let scale = self.get_scale();
 
-                // Draw dialog background
-                let (scaled_x, scaled_y) = self.get_scaled_pos(0.0, dialog::START_Y);
-                let scaled_width = dialog::WIDTH * scale;
-                let scaled_height = dialog::HEIGHT * scale;
+            // Draw dialog background
+            let (scaled_x, scaled_y) = self.get_scaled_pos(0.0, dialog::START_Y);
+            let scaled_width = dialog::WIDTH * scale;
+            let scaled_height = dialog::HEIGHT * scale;
 
-                draw_texture_ex(
-                    dialog_background,
-                    scaled_x,
-                    scaled_y,
-                    WHITE,
-                    DrawTextureParams {
-                        dest_size: Some(Vec2::new(scaled_width, scaled_height)),
-                        ..Default::default()
-                    },
-                );
+            draw_texture_ex(
+                dialog_background,
+                scaled_x,
+                scaled_y,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(Vec2::new(scaled_width, scaled_height)),
+                    ..Default::default()
+                },
+            );
 
-                if let Some(dialog_id) = game.dialog_menu.current_dialog_id {
-                    if let Some(current_scene) = game.get_current_scene() {
-                        if let Some(dialog) =
-                            current_scene.dialogs.iter().find(|d| d.id == dialog_id)
-                        {
-                            if let Some(level) = dialog.tree.get(game.dialog_menu.current_level) {
-                                // Get the font outside the loop
-                                let dialog_font = asset_manager.get_font("dialog");
-
-                                for (i, option) in level.options.iter().enumerate() {
-                                    let option_x = dialog::OPTION_START_X * scale + scaled_x;
-                                    let option_y = (dialog::OPTION_START_Y
-                                        + i as f32 * dialog::OPTION_SPACING)
-                                        * scale
-                                        + scaled_y;
-                                    let option_width = dialog::OPTION_BOX_WIDTH * scale;
-                                    let option_height = dialog::OPTION_BOX_HEIGHT * scale;
-
-                                    let is_hovered = game.dialog_menu.hovered_option == Some(i);
-                                    let (box_color, text_color) = if is_hovered {
-                                        (
-                                            dialog::OPTION_HOVER_BOX_COLOR,
-                                            dialog::OPTION_HOVER_TEXT_COLOR,
-                                        )
-                                    } else {
-                                        (dialog::OPTION_BOX_COLOR, dialog::OPTION_TEXT_COLOR)
-                                    };
-
-                                    if game.debug_tools.active {
-                                        draw_rectangle_lines(
-                                            option_x,
-                                            option_y,
-                                            option_width,
-                                            option_height,
-                                            2.0,
-                                            box_color,
-                                        );
-                                    }
-                                    // Draw option text with custom font
-                                    let font_size = dialog::FONT_SIZE * scale;
-                                    let text_params = TextParams {
-                                        font: dialog_font,
-                                        font_size: font_size as u16,
-                                        color: text_color,
-                                        ..Default::default()
-                                    };
-
-                                    draw_text_ex(
-                                        &option.text,
-                                        option_x + dialog::TEXT_PADDING_X * scale,
-                                        option_y + option_height / 2.0 + font_size / 2.0,
-                                        text_params,
-                                    );
-                                }
-                            }
-                        }
+            if let Some(dialog_id) = game.dialog_menu.current_dialog_id
+                && let Some(current_scene) = game.get_current_scene()
+                && let Some(dialog) = current_scene.dialogs.iter().find(|d| d.id == dialog_id)
+                && let Some(level) = dialog.tree.get(game.dialog_menu.current_level)
+                && !game.voice_queue.is_busy()
+            {
+                // Get the font outside the loop
+                let dialog_font = asset_manager.get_font("dialog");
+
+                for (i, option) in level.options.iter().enumerate() {
+                    let option_x = dialog::OPTION_START_X * scale + scaled_x;
+                    let option_y = (dialog::OPTION_START_Y + i as f32 * dialog::OPTION_SPACING)
+                        * scale
+                        + scaled_y;
+                    let option_width = dialog::OPTION_BOX_WIDTH * scale;
+                    let option_height = dialog::OPTION_BOX_HEIGHT * scale;
+
+                    let is_hovered = game.dialog_menu.hovered_option == Some(i);
+                    let (box_color, text_color) = if is_hovered {
+                        (
+                            dialog::OPTION_HOVER_BOX_COLOR,
+                            dialog::OPTION_HOVER_TEXT_COLOR,
+                        )
+                    } else {
+                        (dialog::OPTION_BOX_COLOR, dialog::OPTION_TEXT_COLOR)
+                    };
+
+                    if game.debug_tools.active {
+                        draw_rectangle_lines(
+                            option_x,
+                            option_y,
+                            option_width,
+                            option_height,
+                            2.0,
+                            box_color,
+                        );
                     }
+                    // Draw option text with custom font
+                    let font_size = dialog::FONT_SIZE * scale;
+                    let text_params = TextParams {
+                        font: dialog_font,
+                        font_size: font_size as u16,
+                        color: text_color,
+                        ..Default::default()
+                    };
+
+                    draw_text_ex(
+                        &option.text,
+                        option_x + dialog::TEXT_PADDING_X * scale,
+                        option_y + option_height / 2.0 + font_size / 2.0,
+                        text_params,
+                    );
                 }
             }
         }
@@ -0,0 +1,291 @@
+use crate::Direction;
+use macroquad::prelude::Vec2;
+use serde::Deserialize;
+use std::collections::HashSet;
+
+// A scripted cutscene: steps run one after another while the player watches.
+// Sequences belong to a scene and either play when the scene is entered or are
+// started by a dialog option.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Sequence {
+    pub name: String,
+    #[serde(default)]
+    pub on_enter: bool,
+    // Play every time it is triggered instead of only the first time
+    #[serde(default)]
+    pub repeat: bool,
+    pub steps: Vec<Step>,
+}
+
+impl Sequence {
+    pub fn textures(&self) -> impl Iterator<Item = &String> {
+        self.steps.iter().filter_map(|step| match step {
+            Step::Show { texture, .. } => Some(texture),
+            _ => None,
+        })
+    }
+
+    // Clips said by a character or NPC, which are played where they stand
+    pub fn voice_lines(&self) -> impl Iterator<Item = &String> {
+        self.steps.iter().filter_map(|step| match step {
+            Step::Say { audio, .. } => audio.as_ref(),
+            _ => None,
+        })
+    }
+
+    // Voice lines, music and sound effects
+    pub fn audio(&self) -> impl Iterator<Item = &String> {
+        self.steps.iter().filter_map(|step| match step {
+            Step::Say { audio, .. } => audio.as_ref(),
+            Step::Music { track } => Some(track),
+            Step::Sound { audio } => Some(audio),
+            _ => None,
+        })
+    }
+}
+
+// Steps that take time (walking, speech, waits and fades) hold the sequence
+// until they finish, unless `wait` is false
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Step {
+    // Walks a character along the grid to a position in game coordinates
+    Walk {
+        character: String,
+        x: f32,
+        y: f32,
+        #[serde(default = "waits")]
+        wait: bool,
+    },
+    Face {
+        character: String,
+        direction: Direction,
+    },
+    // A voice line, a subtitle or both. `text` is looked up in the strings.
+    Say {
+        character: String,
+        #[serde(default)]
+        audio: Option<String>,
+        #[serde(default)]
+        text: Option<String>,
+        #[serde(default = "waits")]
+        wait: bool,
+    },
+    Music {
+        track: String,
+    },
+    Sound {
+        audio: String,
+    },
+    // An image over the scene, full screen when no position is given
+    Show {
+        texture: String,
+        #[serde(default)]
+        x: Option<f32>,
+        #[serde(default)]
+        y: Option<f32>,
+    },
+    // Removes an image, or all of them when no texture is given
+    Hide {
+        #[serde(default)]
+        texture: Option<String>,
+    },
+    Wait {
+        seconds: f32,
+    },
+    // Sets or clears a game flag, which NPC schedules depend on
+    SetFlag {
+        flag: String,
+    },
+    ClearFlag {
+        flag: String,
+    },
+    // Plays a video by name, always waiting for it to end
+    Video {
+        video: String,
+    },
+    // Fades to black (`to` 1) or back to the scene (`to` 0)
+    Fade {
+        to: f32,
+        seconds: f32,
+        #[serde(default = "waits")]
+        wait: bool,
+    },
+}
+
+fn waits() -> bool {
+    true
+}
+
+pub struct Overlay {
+    pub texture: String,
+    // Top left corner, None for full screen
+    pub position: Option<Vec2>,
+}
+
+struct Running {
+    sequence: Sequence,
+    step: usize,
+    started: bool,
+    // Seconds since the current step started
+    elapsed: f32,
+    skipping: bool,
+}
+
+#[derive(Default)]
+struct Fade {
+    from: f32,
+    to: f32,
+    duration: f32,
+    elapsed: f32,
+}
+
+impl Fade {
+    fn amount(&self) -> f32 {
+        if self.elapsed >= self.duration {
+            return self.to;
+        }
+        self.from + (self.to - self.from) * self.elapsed / self.duration
+    }
+}
+
+// Keeps track of the playing sequence and what it shows on screen. The game
+// carries out the steps, the player only decides which step is due.
+#[derive(Default)]
+pub struct SequencePlayer {
+    current: Option<Running>,
+    pub overlays: Vec<Overlay>,
+    fade: Fade,
+    // Names of the sequences that have played
+    played: HashSet<String>,
+}
+
+impl SequencePlayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Returns false when a sequence is already playing or this one has played
+    // and doesn't repeat
+    pub fn start(&mut self, sequence: &Sequence) -> bool {
+        if self.current.is_some() || (!sequence.repeat && self.played.contains(&sequence.name)) {
+            return false;
+        }
+        self.played.insert(sequence.name.clone());
+        self.current = Some(Running {
+            sequence: sequence.clone(),
+            step: 0,
+            started: false,
+            elapsed: 0.0,
+            skipping: false,
+        });
+        true
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.current.is_some()
+    }
+
+    pub fn is_skipping(&self) -> bool {
+        self.current
+            .as_ref()
+            .is_some_and(|running| running.skipping)
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.current
+            .as_ref()
+            .map(|running| running.sequence.name.as_str())
+    }
+
+    // Finishes the rest of the sequence at once. The current step is started
+    // again so it can jump to its end.
+    pub fn skip(&mut self) {
+        if let Some(running) = &mut self.current {
+            running.skipping = true;
+            running.started = false;
+        }
+    }
+
+    pub fn current_step(&self) -> Option<&Step> {
+        let running = self.current.as_ref()?;
+        running.sequence.steps.get(running.step)
+    }
+
+    // True the first time it is called for a step, when the step should start
+    pub fn begin_step(&mut self) -> bool {
+        match &mut self.current {
+            Some(running) if !running.started => {
+                running.started = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn step_elapsed(&self) -> f32 {
+        self.current.as_ref().map_or(0.0, |running| running.elapsed)
+    }
+
+    // Moves on to the next step, ending the sequence after the last one
+    pub fn next_step(&mut self) {
+        let Some(running) = &mut self.current else {
+            return;
+        };
+        running.step += 1;
+        running.started = false;
+        running.elapsed = 0.0;
+        if running.step >= running.sequence.steps.len() {
+            self.stop();
+        }
+    }
+
+    // Ends the sequence. Images and fades only last as long as it does.
+    pub fn stop(&mut self) {
+        self.current = None;
+        self.overlays.clear();
+        self.fade = Fade::default();
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        if let Some(running) = &mut self.current {
+            running.elapsed += delta_time;
+        }
+        self.fade.elapsed += delta_time;
+    }
+
+    pub fn fade_to(&mut self, to: f32, seconds: f32) {
+        self.fade = Fade {
+            from: self.fade.amount(),
+            to: to.clamp(0.0, 1.0),
+            duration: seconds,
+            elapsed: 0.0,
+        };
+    }
+
+    // How much of the screen is faded out, from 0 to 1
+    pub fn fade_amount(&self) -> f32 {
+        self.fade.amount()
+    }
+
+    pub fn show(&mut self, texture: &str, position: Option<Vec2>) {
+        self.hide(Some(texture));
+        self.overlays.push(Overlay {
+            texture: texture.to_string(),
+            position,
+        });
+    }
+
+    pub fn hide(&mut self, texture: Option<&str>) {
+        match texture {
+            Some(texture) => self.overlays.retain(|overlay| overlay.texture != texture),
+            None => self.overlays.clear(),
+        }
+    }
+
+    // Forgets which sequences have played, for a new game
+    pub fn reset(&mut self) {
+        self.stop();
+        self.played.clear();
+    }
+}
@@ -0,0 +1,300 @@
+use crate::audio::{AudioCategory, AudioSystem, Bus};
+use crate::config::settings;
+use crate::error::SettingsError;
+use crate::logging;
+use macroquad::prelude::KeyCode;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct BusSettings {
+    pub volume: f32,
+    pub muted: bool,
+}
+
+impl Default for BusSettings {
+    fn default() -> Self {
+        BusSettings {
+            volume: 1.0,
+            muted: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: BusSettings,
+    pub music: BusSettings,
+    pub ambience: BusSettings,
+    pub dialog: BusSettings,
+    pub sound_effects: BusSettings,
+}
+
+impl AudioSettings {
+    fn bus(&self, bus: Bus) -> &BusSettings {
+        match bus {
+            Bus::Master => &self.master,
+            Bus::Category(AudioCategory::Music) => &self.music,
+            Bus::Category(AudioCategory::Ambience) => &self.ambience,
+            Bus::Category(AudioCategory::Dialog) => &self.dialog,
+            Bus::Category(AudioCategory::SoundEffect) => &self.sound_effects,
+        }
+    }
+
+    fn bus_mut(&mut self, bus: Bus) -> &mut BusSettings {
+        match bus {
+            Bus::Master => &mut self.master,
+            Bus::Category(AudioCategory::Music) => &mut self.music,
+            Bus::Category(AudioCategory::Ambience) => &mut self.ambience,
+            Bus::Category(AudioCategory::Dialog) => &mut self.dialog,
+            Bus::Category(AudioCategory::SoundEffect) => &mut self.sound_effects,
+        }
+    }
+
+    fn buses() -> impl Iterator<Item = Bus> {
+        std::iter::once(Bus::Master).chain(AudioCategory::ALL.into_iter().map(Bus::Category))
+    }
+}
+
+// User preferences, stored as `settings.json` in the user config directory.
+// Missing fields fall back to their defaults, so older files keep working.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub audio: AudioSettings,
+    pub fullscreen: bool,
+    // Window size in windowed mode, the platform default when not set
+    pub resolution: Option<(u32, u32)>,
+    pub language: String,
+    // Multiplier for how long subtitles and dialog text stay on screen
+    pub text_speed: f32,
+    // Seconds per walk animation frame
+    pub animation_speed: f32,
+    // Action name -> key name, e.g. "skip_line": "Period", see input::Bindings.
+    // Only keys the player changed are stored, the rest follow
+    // DEFAULT_KEY_BINDINGS so new defaults reach existing settings files.
+    pub key_bindings: BTreeMap<String, String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            audio: AudioSettings::default(),
+            fullscreen: false,
+            resolution: None,
+            language: settings::DEFAULT_LANGUAGE.to_string(),
+            text_speed: 1.0,
+            animation_speed: settings::DEFAULT_ANIMATION_SPEED,
+            key_bindings: BTreeMap::new(),
+        }
+    }
+}
+
+impl Settings {
+    // Reads the settings file, falling back to the defaults if it is missing or broken
+    pub fn load() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let Some(path) = Self::path() else {
+                return Settings::default();
+            };
+            let json = match std::fs::read_to_string(&path) {
+                Ok(json) => json,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    return Settings::default();
+                }
+                Err(e) => {
+                    log::warn!(target: logging::GAME, "Failed to read {}: {}", path.display(), e);
+                    return Settings::default();
+                }
+            };
+            match serde_json::from_str::<Settings>(&json) {
+                Ok(mut loaded) => {
+                    log::info!(target: logging::GAME, "Loaded settings from {}", path.display());
+                    loaded.animation_speed =
+                        loaded.animation_speed.max(settings::MIN_ANIMATION_SPEED);
+                    loaded
+                }
+                Err(e) => {
+                    log::warn!(
+                        target: logging::GAME,
+                        "Ignoring {}, using default settings: {}",
+                        path.display(),
+                        e
+                    );
+                    Settings::default()
+                }
+            }
+        }
+
+        // The web build has no config directory and always starts with the defaults
+        #[cfg(target_arch = "wasm32")]
+        Settings::default()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self) -> Result<(), SettingsError> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        let io_error = |e: std::io::Error| SettingsError::Io {
+            path: path.display().to_string(),
+            message: e.to_string(),
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(io_error)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| SettingsError::Serialize {
+            message: e.to_string(),
+        })?;
+        std::fs::write(&path, json).map_err(io_error)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self) -> Result<(), SettingsError> {
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn path() -> Option<std::path::PathBuf> {
+        crate::asset_resolver::config_dir().map(|dir| dir.join(crate::config::paths::SETTINGS_FILE))
+    }
+
+    pub fn apply_audio(&self, audio_system: &mut AudioSystem) {
+        for bus in AudioSettings::buses() {
+            let bus_settings = self.audio.bus(bus);
+            audio_system.set_volume(bus, bus_settings.volume);
+            audio_system.set_muted(bus, bus_settings.muted);
+        }
+    }
+
+    // Copies the current mixer state so it is saved with the settings
+    pub fn capture_audio(&mut self, audio_system: &AudioSystem) {
+        for bus in AudioSettings::buses() {
+            *self.audio.bus_mut(bus) = BusSettings {
+                volume: audio_system.get_volume(bus),
+                muted: audio_system.is_muted(bus),
+            };
+        }
+    }
+}
+
+// Key names match macroquad's `KeyCode` variants, e.g. "A", "Key1", "F11", "Period"
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    const KEYS: &[KeyCode] = &[
+        KeyCode::A,
+        KeyCode::B,
+        KeyCode::C,
+        KeyCode::D,
+        KeyCode::E,
+        KeyCode::F,
+        KeyCode::G,
+        KeyCode::H,
+        KeyCode::I,
+        KeyCode::J,
+        KeyCode::K,
+        KeyCode::L,
+        KeyCode::M,
+        KeyCode::N,
+        KeyCode::O,
+        KeyCode::P,
+        KeyCode::Q,
+        KeyCode::R,
+        KeyCode::S,
+        KeyCode::T,
+        KeyCode::U,
+        KeyCode::V,
+        KeyCode::W,
+        KeyCode::X,
+        KeyCode::Y,
+        KeyCode::Z,
+        KeyCode::Key0,
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+        KeyCode::Key8,
+        KeyCode::Key9,
+        KeyCode::F1,
+        KeyCode::F2,
+        KeyCode::F3,
+        KeyCode::F4,
+        KeyCode::F5,
+        KeyCode::F6,
+        KeyCode::F7,
+        KeyCode::F8,
+        KeyCode::F9,
+        KeyCode::F10,
+        KeyCode::F11,
+        KeyCode::F12,
+        KeyCode::Space,
+        KeyCode::Enter,
+        KeyCode::Escape,
+        KeyCode::Tab,
+        KeyCode::Backspace,
+        KeyCode::Period,
+        KeyCode::Comma,
+        KeyCode::Minus,
+        KeyCode::Equal,
+        KeyCode::LeftBracket,
+        KeyCode::RightBracket,
+        KeyCode::Up,
+        KeyCode::Down,
+        KeyCode::Left,
+        KeyCode::Right,
+        KeyCode::PageUp,
+        KeyCode::PageDown,
+        KeyCode::Home,
+        KeyCode::End,
+        KeyCode::LeftShift,
+        KeyCode::RightShift,
+        KeyCode::LeftControl,
+        KeyCode::RightControl,
+        KeyCode::LeftAlt,
+        KeyCode::RightAlt,
+    ];
+    KEYS.iter()
+        .copied()
+        .find(|key| format!("{:?}", key).eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_names_ignore_case() {
+        assert_eq!(key_from_name("Period"), Some(KeyCode::Period));
+        assert_eq!(key_from_name("backspace"), Some(KeyCode::Backspace));
+        assert_eq!(key_from_name("F12"), Some(KeyCode::F12));
+        assert_eq!(key_from_name("key1"), Some(KeyCode::Key1));
+    }
+
+    #[test]
+    fn unknown_key_names_are_none() {
+        assert_eq!(key_from_name(""), None);
+        assert_eq!(key_from_name("Hyper"), None);
+        assert_eq!(key_from_name("1"), None);
+        assert_eq!(key_from_name("Period "), None);
+    }
+
+    #[test]
+    fn partial_settings_keep_defaults() {
+        let settings: Settings =
+            serde_json::from_str(r#"{ "key_bindings": { "skip_line": "K" } }"#).unwrap();
+        assert_eq!(settings.key_bindings["skip_line"], "K");
+        assert_eq!(settings.language, Settings::default().language);
+        assert_eq!(settings.audio, AudioSettings::default());
+    }
+
+    #[test]
+    fn default_settings_store_no_key_bindings() {
+        let json = serde_json::to_value(Settings::default()).unwrap();
+        assert_eq!(json["key_bindings"], serde_json::json!({}));
+    }
+}
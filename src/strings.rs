@@ -0,0 +1,59 @@
+use crate::asset_manager::AssetManager;
+use crate::config::{paths, strings};
+use crate::error::{AssetError, DataError};
+use crate::logging;
+use std::collections::HashMap;
+
+// Text shown to the player. Entries are looked up by key ("hover.take") or, for
+// names that come from level_data.json, by the original Swedish text ("Dynamit 1").
+// Swedish is built in, other languages are read from `lang/<language>.json`.
+pub struct Strings {
+    table: HashMap<String, String>,
+}
+
+impl Strings {
+    pub async fn load(asset_manager: &AssetManager, language: &str) -> Self {
+        let mut table: HashMap<String, String> = strings::DEFAULTS
+            .iter()
+            .map(|&(key, text)| (key.to_string(), text.to_string()))
+            .collect();
+
+        if language != strings::BUILT_IN_LANGUAGE {
+            let path = format!("{}/{}.json", paths::LANGUAGE_DIR, language);
+            match asset_manager
+                .load_data_json::<HashMap<String, String>>(&path)
+                .await
+            {
+                Ok(translations) => {
+                    log::info!(
+                        target: logging::ASSETS,
+                        "Loaded {} strings for language {:?}",
+                        translations.len(),
+                        language
+                    );
+                    table.extend(translations);
+                }
+                Err(DataError::Load(AssetError::NotFound { .. })) => {
+                    log::warn!(
+                        target: logging::ASSETS,
+                        "No strings for language {:?}, using Swedish",
+                        language
+                    );
+                }
+                Err(e) => log::warn!(target: logging::ASSETS, "{}", e),
+            }
+        }
+
+        Strings { table }
+    }
+
+    // The text for a key or original text, the text itself when it has no entry
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.table.get(key).map_or(key, String::as_str)
+    }
+
+    // Fills the `{object}` placeholder of a template, e.g. "Ta {object}"
+    pub fn format(&self, key: &str, object: &str) -> String {
+        self.get(key).replace("{object}", object)
+    }
+}
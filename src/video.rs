@@ -0,0 +1,170 @@
+use macroquad::time::get_time;
+use serde::Deserialize;
+
+// A full-motion video, extracted as numbered frame images and a separate
+// soundtrack, e.g. with `ffmpeg -i INTRO.AVI Film/intro/%04d.png intro.wav`
+#[derive(Deserialize, Debug, Clone)]
+pub struct Video {
+    pub name: String,
+    // Frame paths in ffmpeg's numbering style, `%d` or zero padded like `%04d`
+    pub frames: String,
+    pub frame_count: u32,
+    pub fps: f32,
+    // Number of the first frame, ffmpeg starts at 1
+    #[serde(default = "first_frame")]
+    pub first_frame: u32,
+    #[serde(default)]
+    pub audio: Option<String>,
+}
+
+fn first_frame() -> u32 {
+    1
+}
+
+impl Video {
+    // Path of a frame, counted from 0
+    pub fn frame_path(&self, index: u32) -> String {
+        let number = self.first_frame + index;
+        let Some(start) = self.frames.find('%') else {
+            return self.frames.clone();
+        };
+        let Some(end) = self.frames[start..].find('d').map(|end| start + end) else {
+            return self.frames.clone();
+        };
+        let width = self.frames[start + 1..end].parse::<usize>().unwrap_or(0);
+        format!(
+            "{}{:0width$}{}",
+            &self.frames[..start],
+            number,
+            &self.frames[end + 1..],
+            width = width
+        )
+    }
+}
+
+struct Playing {
+    video: Video,
+    started_at: f64,
+    // Frame on screen, the last one that could be loaded
+    shown: Option<u32>,
+    // Frames with a loaded texture
+    loaded: Vec<u32>,
+}
+
+// Times the frames of the playing video against the clock, which the
+// soundtrack also follows. The game loads the frames that are due.
+pub struct VideoPlayer {
+    current: Option<Playing>,
+}
+
+impl VideoPlayer {
+    pub fn new() -> Self {
+        VideoPlayer { current: None }
+    }
+
+    pub fn start(&mut self, video: Video) {
+        self.current = Some(Playing {
+            video,
+            started_at: get_time(),
+            shown: None,
+            loaded: Vec::new(),
+        });
+    }
+
+    // Ends playback, returning the video and the frames that were loaded
+    pub fn stop(&mut self) -> Option<(Video, Vec<u32>)> {
+        self.current
+            .take()
+            .map(|playing| (playing.video, playing.loaded))
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.current.is_some()
+    }
+
+    pub fn video(&self) -> Option<&Video> {
+        self.current.as_ref().map(|playing| &playing.video)
+    }
+
+    // The frame that should be on screen now, None once the video has ended
+    pub fn due_frame(&self) -> Option<u32> {
+        let playing = self.current.as_ref()?;
+        let elapsed = (get_time() - playing.started_at) as f32;
+        let frame = (elapsed * playing.video.fps) as u32;
+        (frame < playing.video.frame_count).then_some(frame)
+    }
+
+    pub fn mark_loaded(&mut self, frame: u32) {
+        if let Some(playing) = &mut self.current
+            && !playing.loaded.contains(&frame)
+        {
+            playing.loaded.push(frame);
+        }
+    }
+
+    pub fn show(&mut self, frame: u32) {
+        if let Some(playing) = &mut self.current {
+            playing.shown = Some(frame);
+        }
+    }
+
+    // Frames before the one on screen, which won't be needed again
+    pub fn take_stale_frames(&mut self) -> Vec<u32> {
+        let Some(playing) = &mut self.current else {
+            return Vec::new();
+        };
+        let Some(shown) = playing.shown else {
+            return Vec::new();
+        };
+        let (stale, keep) = playing.loaded.iter().partition(|&&frame| frame < shown);
+        playing.loaded = keep;
+        stale
+    }
+
+    pub fn shown_frame_path(&self) -> Option<String> {
+        let playing = self.current.as_ref()?;
+        playing.shown.map(|frame| playing.video.frame_path(frame))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video(frames: &str, first_frame: u32) -> Video {
+        Video {
+            name: "intro".to_string(),
+            frames: frames.to_string(),
+            frame_count: 10,
+            fps: 15.0,
+            first_frame,
+            audio: None,
+        }
+    }
+
+    #[test]
+    fn zero_padded_frames() {
+        let video = video("Film/intro/%04d.png", 1);
+        assert_eq!(video.frame_path(0), "Film/intro/0001.png");
+        assert_eq!(video.frame_path(11), "Film/intro/0012.png");
+    }
+
+    #[test]
+    fn unpadded_frames_from_zero() {
+        let video = video("Film/intro/frame%d.png", 0);
+        assert_eq!(video.frame_path(0), "Film/intro/frame0.png");
+        assert_eq!(video.frame_path(120), "Film/intro/frame120.png");
+    }
+
+    #[test]
+    fn numbers_wider_than_the_padding() {
+        let video = video("%02d.png", 1);
+        assert_eq!(video.frame_path(99), "100.png");
+    }
+
+    #[test]
+    fn paths_without_a_pattern_are_kept() {
+        assert_eq!(video("Film/still.png", 1).frame_path(3), "Film/still.png");
+        assert_eq!(video("Film/100%.png", 1).frame_path(3), "Film/100%.png");
+    }
+}
@@ -0,0 +1,223 @@
+use crate::asset_manager::AssetManager;
+use crate::audio::{AudioCategory, AudioSystem, Emitter};
+use crate::config::voice;
+use crate::logging;
+use macroquad::time::get_time;
+use std::collections::VecDeque;
+
+// A single spoken clip and who says it. Speakers are identified by name, either
+// a playable character (`sickan`) or the description of a dialog (`Doris`).
+// Lines with an empty path have no clip and only show their text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VoiceLine {
+    pub path: String,
+    pub speaker: String,
+    pub emitter: Option<Emitter>,
+    // Subtitle shown while the line plays
+    pub text: Option<String>,
+}
+
+impl VoiceLine {
+    pub fn new(path: impl Into<String>, speaker: impl Into<String>) -> Self {
+        VoiceLine {
+            path: path.into(),
+            speaker: speaker.into(),
+            emitter: None,
+            text: None,
+        }
+    }
+
+    pub fn with_emitter(mut self, emitter: Emitter) -> Self {
+        self.emitter = Some(emitter);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum VoiceEvent {
+    LineStarted(VoiceLine),
+    LineFinished { line: VoiceLine, skipped: bool },
+    // The last queued line has finished and nobody is speaking
+    QueueFinished,
+}
+
+struct CurrentLine {
+    line: VoiceLine,
+    started_at: f64,
+    duration: f32,
+}
+
+// Plays voice lines one after another on the dialog bus and reports when each
+// of them ends, so dialogs and scripted sequences can wait for the speech.
+pub struct VoiceQueue {
+    queue: VecDeque<VoiceLine>,
+    current: Option<CurrentLine>,
+    events: Vec<VoiceEvent>,
+    // Multiplier for how long lines without a clip stay on screen
+    text_speed: f32,
+}
+
+impl VoiceQueue {
+    pub fn new(text_speed: f32) -> Self {
+        VoiceQueue {
+            queue: VecDeque::new(),
+            current: None,
+            events: Vec::new(),
+            text_speed,
+        }
+    }
+
+    // Interrupts whatever is being said and plays the lines in order
+    pub fn play(&mut self, audio_system: &mut AudioSystem, lines: Vec<VoiceLine>) {
+        self.queue = lines.into();
+        self.finish_current(audio_system, true);
+    }
+
+    // Plays a line after the ones already queued
+    pub fn enqueue(&mut self, line: VoiceLine) {
+        self.queue.push_back(line);
+    }
+
+    pub fn skip_line(&mut self, audio_system: &mut AudioSystem) {
+        if self.current.is_some() {
+            self.finish_current(audio_system, true);
+        }
+    }
+
+    // Starts the next line once the current one has ended. Should be called
+    // once per frame, before the audio system is updated.
+    pub fn update(&mut self, audio_system: &mut AudioSystem, asset_manager: &AssetManager) {
+        self.update_at(get_time(), audio_system, asset_manager);
+    }
+
+    fn update_at(
+        &mut self,
+        now: f64,
+        audio_system: &mut AudioSystem,
+        asset_manager: &AssetManager,
+    ) {
+        if let Some(current) = &self.current {
+            let elapsed = (now - current.started_at) as f32;
+            if elapsed < current.duration {
+                return;
+            }
+            self.finish_current(audio_system, false);
+        }
+
+        if let Some(line) = self.queue.pop_front() {
+            let duration = if line.path.is_empty() {
+                self.text_duration(&line)
+            } else if let Err(e) = audio_system.play_audio_at(
+                asset_manager,
+                &line.path,
+                AudioCategory::Dialog,
+                line.emitter,
+            ) {
+                // A clip that failed to play is shown like a line without one,
+                // so whoever waits for it still hears back
+                log::warn!(target: logging::AUDIO, "{}", e);
+                if line.text.is_some() {
+                    self.text_duration(&line)
+                } else {
+                    voice::UNKNOWN_LINE_LENGTH
+                }
+            } else {
+                // Clips that could not be measured get a fixed length so the queue
+                // never stalls on them
+                asset_manager
+                    .get_sound_duration(&line.path)
+                    .unwrap_or(voice::UNKNOWN_LINE_LENGTH)
+            };
+            log::debug!(
+                target: logging::DIALOG,
+                "{} says {} ({:.1}s)",
+                line.speaker,
+                line.path,
+                duration
+            );
+            self.events.push(VoiceEvent::LineStarted(line.clone()));
+            self.current = Some(CurrentLine {
+                line,
+                started_at: now,
+                duration,
+            });
+        }
+    }
+
+    fn text_duration(&self, line: &VoiceLine) -> f32 {
+        let length = line.text.as_ref().map_or(0, |text| text.chars().count());
+        (length as f32 * voice::TEXT_SECONDS_PER_CHAR).max(voice::TEXT_MIN_LENGTH) * self.text_speed
+    }
+
+    // True while a line is playing or waiting to be played
+    pub fn is_busy(&self) -> bool {
+        self.current.is_some() || !self.queue.is_empty()
+    }
+
+    // The line being said and how many seconds of it have played
+    pub fn current_line(&self) -> Option<(&VoiceLine, f32)> {
+        self.current
+            .as_ref()
+            .map(|c| (&c.line, (get_time() - c.started_at) as f32))
+    }
+
+    pub fn current_speaker(&self) -> Option<&str> {
+        self.current.as_ref().map(|c| c.line.speaker.as_str())
+    }
+
+    pub fn take_events(&mut self) -> Vec<VoiceEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn finish_current(&mut self, audio_system: &mut AudioSystem, skipped: bool) {
+        let Some(current) = self.current.take() else {
+            return;
+        };
+        if skipped && !current.line.path.is_empty() {
+            audio_system.stop(AudioCategory::Dialog, &current.line.path);
+        }
+        self.events.push(VoiceEvent::LineFinished {
+            line: current.line,
+            skipped,
+        });
+        if self.queue.is_empty() {
+            self.events.push(VoiceEvent::QueueFinished);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset_resolver::AssetResolver;
+
+    #[test]
+    fn missing_clip_still_finishes() {
+        let mut audio_system = AudioSystem::new();
+        let asset_manager = AssetManager::new(AssetResolver::new(Vec::new()));
+        let mut queue = VoiceQueue::new(1.0);
+        let line = VoiceLine::new("missing.wav", "sickan");
+        queue.enqueue(line.clone());
+
+        queue.update_at(0.0, &mut audio_system, &asset_manager);
+        assert_eq!(
+            queue.take_events(),
+            vec![VoiceEvent::LineStarted(line.clone())]
+        );
+        assert!(queue.is_busy());
+
+        let end = voice::UNKNOWN_LINE_LENGTH as f64;
+        queue.update_at(end, &mut audio_system, &asset_manager);
+        assert_eq!(
+            queue.take_events(),
+            vec![
+                VoiceEvent::LineFinished {
+                    line,
+                    skipped: false
+                },
+                VoiceEvent::QueueFinished,
+            ]
+        );
+        assert!(!queue.is_busy());
+    }
+}